@@ -0,0 +1,188 @@
+//! encodes instructions by hand, so test programs read like assembly instead of raw opcodes
+//!
+//! ```
+//! use ataritron::assemble::*;
+//!
+//! let rom = Program::new()
+//!     .op(lda_imm(0xba))
+//!     .op(sta_zp(0x05))
+//!     .op(jmp_abs(0x1000))
+//!     .build();
+//! assert_eq!(rom, vec![0xa9, 0xba, 0x85, 0x05, 0x4c, 0x00, 0x10]);
+//! ```
+
+use crate::cpu::{index_register, opcode_info, Addressing, Instruction};
+use crate::memory::{Memory, OutOfRangeError};
+
+fn with_word(opcode : u8, word : u16) -> Vec<u8> {
+    let [low, high] = word.to_le_bytes();
+    vec![opcode, low, high]
+}
+
+pub fn lda_imm(value : u8) -> Vec<u8> { vec![0xa9, value] }
+pub fn lda_zp(addr : u8) -> Vec<u8> { vec![0xa5, addr] }
+pub fn lda_zp_x(addr : u8) -> Vec<u8> { vec![0xb5, addr] }
+pub fn lda_abs(addr : u16) -> Vec<u8> { with_word(0xad, addr) }
+pub fn lda_abs_x(addr : u16) -> Vec<u8> { with_word(0xbd, addr) }
+pub fn lda_abs_y(addr : u16) -> Vec<u8> { with_word(0xb9, addr) }
+pub fn lda_ind_x(addr : u8) -> Vec<u8> { vec![0xa1, addr] }
+pub fn lda_ind_y(addr : u8) -> Vec<u8> { vec![0xb1, addr] }
+
+pub fn ldx_imm(value : u8) -> Vec<u8> { vec![0xa2, value] }
+pub fn ldx_zp(addr : u8) -> Vec<u8> { vec![0xa6, addr] }
+pub fn ldx_abs(addr : u16) -> Vec<u8> { with_word(0xae, addr) }
+pub fn ldy_imm(value : u8) -> Vec<u8> { vec![0xa0, value] }
+pub fn ldy_zp(addr : u8) -> Vec<u8> { vec![0xa4, addr] }
+pub fn ldy_abs(addr : u16) -> Vec<u8> { with_word(0xac, addr) }
+
+pub fn sta_zp(addr : u8) -> Vec<u8> { vec![0x85, addr] }
+pub fn sta_zp_x(addr : u8) -> Vec<u8> { vec![0x95, addr] }
+pub fn sta_abs(addr : u16) -> Vec<u8> { with_word(0x8d, addr) }
+pub fn sta_abs_x(addr : u16) -> Vec<u8> { with_word(0x9d, addr) }
+pub fn sta_abs_y(addr : u16) -> Vec<u8> { with_word(0x99, addr) }
+pub fn stx_zp(addr : u8) -> Vec<u8> { vec![0x86, addr] }
+pub fn stx_abs(addr : u16) -> Vec<u8> { with_word(0x8e, addr) }
+pub fn sty_zp(addr : u8) -> Vec<u8> { vec![0x84, addr] }
+pub fn sty_abs(addr : u16) -> Vec<u8> { with_word(0x8c, addr) }
+
+pub fn jmp_abs(addr : u16) -> Vec<u8> { with_word(0x4c, addr) }
+pub fn jmp_ind(addr : u16) -> Vec<u8> { with_word(0x6c, addr) }
+pub fn jsr(addr : u16) -> Vec<u8> { with_word(0x20, addr) }
+pub fn rts() -> Vec<u8> { vec![0x60] }
+pub fn brk() -> Vec<u8> { vec![0x00] }
+pub fn rti() -> Vec<u8> { vec![0x40] }
+pub fn nop() -> Vec<u8> { vec![0xea] }
+
+pub fn clc() -> Vec<u8> { vec![0x18] }
+pub fn sec() -> Vec<u8> { vec![0x38] }
+pub fn cld() -> Vec<u8> { vec![0xd8] }
+pub fn sed() -> Vec<u8> { vec![0xf8] }
+pub fn cli() -> Vec<u8> { vec![0x58] }
+pub fn sei() -> Vec<u8> { vec![0x78] }
+pub fn clv() -> Vec<u8> { vec![0xb8] }
+pub fn php() -> Vec<u8> { vec![0x08] }
+pub fn plp() -> Vec<u8> { vec![0x28] }
+
+/// concatenates encoded instructions
+#[derive(Debug, Default)]
+pub struct Program {
+    bytes : Vec<u8>
+}
+
+impl Program {
+    pub fn new() -> Program {
+        Program { bytes: Vec::new() }
+    }
+
+    pub fn op(mut self, bytes : Vec<u8>) -> Self {
+        self.bytes.extend(bytes);
+        self
+    }
+
+    /// raw bytes, for data or opcodes without a helper
+    pub fn bytes(mut self, bytes : &[u8]) -> Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// turns a decoded instruction back into bytes, the opposite of what the cpu's fetch does.
+/// the cycle count is ignored. indexed operands don't say which register they use, so X wins
+/// when the operation can use both. None when no opcode has that operation and mode
+pub fn encode(instruction : &Instruction) -> Option<Vec<u8>> {
+    let mode = instruction.addressing.mode();
+    let opcode = (0..=0xff_u8)
+        .filter(|&opcode| opcode_info(opcode).is_some_and(|info| info.operation == instruction.operation && info.mode == mode))
+        .min_by_key(|&opcode| index_register(opcode) != 'X')?;
+
+    Some(match instruction.addressing {
+        Addressing::Implied => vec![opcode],
+        Addressing::Immediate(byte)
+            | Addressing::Zeropage(byte)
+            | Addressing::IndexedZeropage(byte, _)
+            | Addressing::PreindexedIndirect(byte, _)
+            | Addressing::PostindexedIndirect(byte, _)
+            | Addressing::RelativeAddress(byte)
+        => vec![opcode, byte],
+        Addressing::Absolute(word)
+            | Addressing::IndexedAbsolute(word, _)
+            | Addressing::Indirect(word)
+        => with_word(opcode, word)
+    })
+}
+
+/// encodes `program` and loads it at `org`, returning the address right after it.
+/// panics on instructions `encode` can't do, like a STA with an immediate operand
+pub fn load_program(mem : &mut Memory, org : u16, program : &[Instruction]) -> Result<u16, OutOfRangeError> {
+    let bytes : Vec<u8> = program.iter()
+        .flat_map(|instruction| encode(instruction).unwrap_or_else(|| panic!("no opcode for {:?}", instruction)))
+        .collect();
+
+    mem.load_rom_at_org(org, &bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{Cpu, Operations};
+
+    #[test]
+    fn assembles_like_hand_encoding() {
+        let rom = Program::new()
+            .op(lda_imm(0xba))
+            .op(ldx_abs(0x2350))
+            .op(lda_abs_y(0x4277))
+            .op(sta_zp(0x05))
+            .op(lda_ind_y(0x33))
+            .op(nop())
+            .bytes(&[0xff])
+            .op(jmp_abs(0x1000))
+            .build();
+
+        assert_eq!(rom, vec![
+            0xa9, 0xba,
+            0xae, 0x50, 0x23,
+            0xb9, 0x77, 0x42,
+            0x85, 0x05,
+            0xb1, 0x33,
+            0xea,
+            0xff,
+            0x4c, 0x00, 0x10
+        ]);
+    }
+
+    #[test]
+    fn encoded_programs_decode_to_the_same_instructions() {
+        let program = [
+            Instruction { operation: Operations::LoadAccumulator, addressing: Addressing::Immediate(0x42), cycle_count: 2 },
+            Instruction { operation: Operations::StoreAccumulator, addressing: Addressing::IndexedAbsolute(0x2000, 0x00), cycle_count: 5 },
+            Instruction { operation: Operations::LoadX, addressing: Addressing::IndexedZeropage(0x80, 0x00), cycle_count: 4 },
+            Instruction { operation: Operations::Jump, addressing: Addressing::Indirect(0x3000), cycle_count: 5 },
+            Instruction { operation: Operations::BranchOnNotEqual, addressing: Addressing::RelativeAddress(0xfe), cycle_count: 2 },
+            Instruction { operation: Operations::ArithmeticShiftLeft, addressing: Addressing::Implied, cycle_count: 2 },
+        ];
+
+        let mut mem = Memory::new(64*1024).unwrap();
+        let end = load_program(&mut mem, 0x1000, &program).unwrap();
+        assert_eq!(0x100d, end);
+        assert_eq!(&[0xa9, 0x42, 0x9d, 0x00, 0x20, 0xb6, 0x80], mem.load_slice(0x1000, 7).unwrap());
+
+        let cpu = Cpu::new(mem);
+        let mut addr = 0x1000;
+        for instruction in program {
+            let (decoded, size) = cpu.decode_at(addr).unwrap();
+            assert_eq!(instruction, decoded);
+            addr += size;
+        }
+    }
+
+    #[test]
+    fn cannot_encode_missing_modes() {
+        let sta_imm = Instruction { operation: Operations::StoreAccumulator, addressing: Addressing::Immediate(0x10), cycle_count: 2 };
+        assert_eq!(None, encode(&sta_imm));
+    }
+}
@@ -0,0 +1,193 @@
+use std::cell::RefCell;
+
+use crate::bus::{Bus, CartBackend, MemoryMappedDevice};
+use crate::memory::OutOfRangeError;
+use crate::riot::Riot;
+use crate::tia::Tia;
+
+/// what an address reaches on the 2600. only A0-A12 are decoded, and the chips only look at
+/// some of those, so each of them shows up in several places
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AtariAddress {
+    /// a TIA register, A7 low. only A0-A5 are decoded
+    Tia(u8),
+    /// one of the 128 bytes of RAM in the RIOT, A7 high and A9 low
+    Ram(u8),
+    /// a RIOT register, A7 and A9 high
+    Riot(u8),
+    /// an offset into the 4K cartridge window, A12 high
+    Cartridge(u16)
+}
+
+impl AtariAddress {
+    pub(crate) fn decode(addr : u16) -> AtariAddress {
+        if addr & 0x1000 != 0 {
+            AtariAddress::Cartridge(addr & 0x0fff)
+        } else if addr & 0x0080 == 0 {
+            AtariAddress::Tia((addr & 0x3f) as u8)
+        } else if addr & 0x0200 == 0 {
+            AtariAddress::Ram((addr & 0x7f) as u8)
+        } else {
+            AtariAddress::Riot((addr & 0x1f) as u8)
+        }
+    }
+}
+
+/// the 2600's address space with each chip on its own: the 128 bytes of RAM, the RIOT,
+/// the TIA and whatever cartridge is plugged in. only the 13 address lines the 6507 has
+/// are decoded, so every address lands somewhere
+pub struct AtariBus {
+    ram : [u8; 128],
+    riot : Riot,
+    tia : RefCell<Box<dyn MemoryMappedDevice>>,
+    cart : Box<dyn CartBackend>
+}
+
+impl AtariBus {
+    pub fn new(cart : Box<dyn CartBackend>) -> AtariBus {
+        AtariBus {
+            ram: [0x00; 128],
+            riot: Riot::new(),
+            tia: RefCell::new(Box::new(Tia::new())),
+            cart
+        }
+    }
+
+    pub fn riot_mut(&mut self) -> &mut Riot {
+        &mut self.riot
+    }
+
+    /// puts `device` behind the TIA registers instead of the stub
+    pub fn set_tia(&mut self, device : Box<dyn MemoryMappedDevice>) {
+        self.tia = RefCell::new(device);
+    }
+}
+
+impl Bus for AtariBus {
+    fn load(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+        Ok(match AtariAddress::decode(addr) {
+            AtariAddress::Cartridge(offset) => self.cart.read(offset),
+            AtariAddress::Tia(reg) => self.tia.borrow_mut().read(reg),
+            AtariAddress::Ram(byte) => self.ram[byte as usize],
+            AtariAddress::Riot(reg) => self.riot.read(reg)
+        })
+    }
+
+    fn store(&mut self, addr : u16, v : u8) -> Result<(), OutOfRangeError> {
+        match AtariAddress::decode(addr) {
+            AtariAddress::Cartridge(offset) => self.cart.write(offset, v),
+            AtariAddress::Tia(reg) => self.tia.get_mut().write(reg, v),
+            AtariAddress::Ram(byte) => self.ram[byte as usize] = v,
+            AtariAddress::Riot(reg) => self.riot.write(reg, v)
+        }
+
+        Ok(())
+    }
+
+    fn peek(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+        match AtariAddress::decode(addr) {
+            AtariAddress::Cartridge(offset) => Ok(self.cart.peek(offset)),
+            _ => self.load(addr)
+        }
+    }
+
+    fn tick(&mut self, cycles : u8) {
+        self.riot.tick(cycles);
+        self.tia.get_mut().tick(cycles);
+    }
+
+    fn clear_ram(&mut self) {
+        self.ram = [0x00; 128];
+    }
+
+    fn reset_devices(&mut self) {
+        self.riot.reset();
+        self.tia.get_mut().reset();
+    }
+
+    fn ready(&self) -> bool {
+        self.tia.borrow().ready()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Cartridge;
+    use crate::riot::INTIM;
+
+    fn bus() -> AtariBus {
+        let image : Vec<u8> = (0..0x1000).map(|i| (i & 0xff) as u8).collect();
+        AtariBus::new(Box::new(Cartridge::new(&image).unwrap()))
+    }
+
+    #[test]
+    fn ram_is_writable_and_the_cartridge_is_not() {
+        let mut bus = bus();
+
+        bus.store(0x0080, 0x42).unwrap();
+        assert_eq!(0x42, bus.load(0x0080).unwrap());
+        assert_eq!(0x42, bus.load(0x0180).unwrap()); // the stack page is the same RAM
+
+        bus.store(0x1010, 0x99).unwrap();
+        assert_eq!(0x10, bus.load(0x1010).unwrap());
+        assert_eq!(0x10, bus.load(0xf010).unwrap()); // A13-A15 aren't wired
+    }
+
+    #[test]
+    fn chips_get_their_registers() {
+        let mut bus = bus();
+
+        bus.store(0x0296, 0x10).unwrap(); // TIM64T
+        assert_eq!(0x10, bus.load(0x0280 | INTIM as u16).unwrap());
+
+        bus.store(0x0009, 0x2c).unwrap(); // COLUBK
+        assert_eq!(0x2c, bus.load(0x0049).unwrap());
+
+        bus.store(0x00ff, 0x01).unwrap();
+        bus.clear_ram();
+        assert_eq!(0x00, bus.load(0x00ff).unwrap());
+    }
+
+    #[test]
+    fn decodes_like_the_2600() {
+        assert_eq!(AtariAddress::Tia(0x09), AtariAddress::decode(0x0049));
+        assert_eq!(AtariAddress::Ram(0x7f), AtariAddress::decode(0x01ff));
+        assert_eq!(AtariAddress::Riot(0x16), AtariAddress::decode(0x0a96));
+        assert_eq!(AtariAddress::Cartridge(0x0ffc), AtariAddress::decode(0xfffc));
+    }
+
+    #[test]
+    fn agrees_with_the_atari_memory() {
+        let mut bus = bus();
+        let mut mem = crate::memory::Memory::new_atari();
+        mem.set_6507_addressing(true);
+
+        for (addr, value) in [(0x01c5, 0x11), (0x0149, 0x22), (0x0a96, 0x03)] {
+            bus.store(addr, value).unwrap();
+            mem.store(addr, value).unwrap();
+        }
+        for addr in [0x00c5, 0x2dc5, 0x0009, 0x4309, 0x0284, 0xe384] {
+            assert_eq!(mem.load(addr).unwrap(), bus.load(addr).unwrap(), "{:04x}", addr);
+        }
+    }
+
+    #[test]
+    fn runs_a_cartridge() {
+        let mut image = vec![0xea; 0x1000];
+        image[0x0000..0x0006].copy_from_slice(&[
+            0xa9, 0x42, // LDA #$42
+            0x85, 0x80, // STA $80
+            0xa6, 0x80  // LDX $80
+        ]);
+        image[0x0ffc..0x0ffe].copy_from_slice(&[0x00, 0xf0]);
+        let bus = AtariBus::new(Box::new(Cartridge::new(&image).unwrap()));
+
+        let mut cpu = crate::cpu::Cpu::new_from_reset(bus).unwrap();
+        assert_eq!(0xf000, cpu.pc());
+        for _ in 0..3 {
+            cpu.step().unwrap();
+        }
+        assert_eq!(0x42, cpu.x());
+    }
+}
@@ -0,0 +1,89 @@
+use crate::memory::OutOfRangeError;
+
+/// everything the cpu can address goes through a bus, so memory-mapped
+/// devices can sit next to plain memory
+pub trait Bus {
+    fn load(&self, addr : u16) -> Result<u8, OutOfRangeError>;
+    fn store(&mut self, addr : u16, v : u8) -> Result<(), OutOfRangeError>;
+
+    /// reads without the side effects a load can have, like switching cartridge banks,
+    /// for debuggers and disassemblers looking at memory
+    fn peek(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+        self.load(addr)
+    }
+
+    /// lets timers and other peripherals catch up with the cycles the cpu just spent
+    fn tick(&mut self, _cycles : u8) {}
+
+    /// forgets what RAM held, like cutting the power does. ROM stays
+    fn clear_ram(&mut self) {}
+
+    /// puts the chips on the bus back the way they power up, see `MemoryMappedDevice::reset`
+    fn reset_devices(&mut self) {}
+
+    /// the RDY line as driven by the devices on the bus. the cpu halts while it's low
+    fn ready(&self) -> bool {
+        true
+    }
+
+    /// how many addresses, counting from 0, have something behind them.
+    /// see `Cpu::set_pc_wrap` for memories smaller than 64K
+    fn address_space(&self) -> u32 {
+        0x10000
+    }
+}
+
+/// a chip living behind a few registers of the address space, like the TIA.
+/// `reg` is relative to where the device is mapped
+pub trait MemoryMappedDevice : CloneDevice {
+    fn read(&mut self, reg : u8) -> u8;
+    fn write(&mut self, reg : u8, value : u8);
+
+    /// the cycles the cpu just spent
+    fn tick(&mut self, _cycles : u8) {}
+
+    /// whether the device lets the cpu run, see `Bus::ready`
+    fn ready(&self) -> bool {
+        true
+    }
+
+    /// back to the power-up state, for a cold boot
+    fn reset(&mut self) {}
+
+    /// whatever the device needs to pick up where it was, for save states.
+    /// devices without state save nothing
+    fn save_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// restores what `save_state` returned. false, leaving the device alone, if `state`
+    /// couldn't have come from this kind of device
+    fn load_state(&mut self, state : &[u8]) -> bool {
+        state.is_empty()
+    }
+}
+
+/// what sits in the 4K cartridge window. `offset` is relative to the start of the window
+pub trait CartBackend {
+    fn read(&self, offset : u16) -> u8;
+
+    /// `read` without switching banks
+    fn peek(&self, offset : u16) -> u8 {
+        self.read(offset)
+    }
+
+    /// ROM ignores writes, but bank switching cartridges watch them
+    fn write(&mut self, _offset : u16, _value : u8) {}
+}
+
+/// copies a boxed device, so a memory holding one can be cloned.
+/// every `Clone` device gets it for free
+pub trait CloneDevice {
+    fn clone_device(&self) -> Box<dyn MemoryMappedDevice>;
+}
+
+impl<T : MemoryMappedDevice + Clone + 'static> CloneDevice for T {
+    fn clone_device(&self) -> Box<dyn MemoryMappedDevice> {
+        Box::new(self.clone())
+    }
+}
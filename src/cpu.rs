@@ -1,17 +1,48 @@
 mod instructions;
 mod flags;
 mod errors;
+mod disassembler;
+mod savestate;
+mod input;
+mod debugger;
+mod builder;
+mod interrupts;
+mod opcode_info;
+mod arithmetic;
 
 #[cfg(test)]
 mod tests;
 
-use std::error::Error;
+use std::collections::HashSet;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::bus::Bus;
 use crate::memory::{Memory, OutOfRangeError};
-use instructions::*;
-use self::errors::CpuError;
+pub use self::instructions::{Addressing, AddressingMode, Instruction, Operations};
+pub use self::opcode_info::{opcode_info, OpcodeInfo};
+pub use self::errors::CpuError;
+pub use self::flags::{CpuFlags, Flags};
+pub use self::debugger::{RunResult, StepReport, StopReason};
+use self::debugger::{AccessLog, BoundaryCheck};
+pub use self::builder::CpuBuilder;
+pub(crate) use self::opcode_info::index_register;
+
+/// receives the address, the decoded instruction and the registers before it executes
+pub type TraceHook = Box<dyn FnMut(u16, &Instruction, &CpuState)>;
+
+/// receives the cycles each instruction took, to keep peripherals in step with the cpu
+pub type ClockSink = Box<dyn FnMut(u64)>;
+
+/// receives the address of an instruction that jumped into the middle of another, and the target
+pub type BoundaryHook = Box<dyn FnMut(u16, u16)>;
 
-pub struct Cpu {
+/// receives the address of a SED run while decimal mode is turned off
+pub type DecimalWarning = Box<dyn FnMut(u16)>;
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Cpu<B: Bus = Memory> {
     /// program counter
     pc: u16,
 
@@ -30,14 +61,146 @@ pub struct Cpu {
     /// status register (flags)
     sr: u8,
 
-    memory: Memory,
+    memory: B,
 
     /// how many cycles the cpu needs to complete the running instruction
-    cycles_busy: u8
+    cycles_busy: u8,
+
+    /// cycles run since the cpu was created
+    cycles: u64,
+
+    /// cycles the last step took, penalties included
+    last_cycles: u8,
+
+    /// the RDY line. while low the cpu just burns cycles
+    ready: bool,
+
+    /// the IRQ line, held by a device until its handler acknowledges it. see `set_irq_line`
+    #[cfg_attr(feature = "serde", serde(default))]
+    irq_line: bool,
+
+    /// the I flag from before a CLI, SEI or PLP, which the next interrupt poll still sees
+    #[cfg_attr(feature = "serde", serde(default))]
+    delayed_interrupt_disable: Option<bool>,
+
+    variant: CpuVariant,
+
+    /// ADC and SBC stay binary even with D set, see `set_decimal_supported`
+    #[cfg_attr(feature = "serde", serde(default))]
+    binary_only: bool,
+
+    /// pushes and pulls fail instead of wrapping around the stack page, see `set_strict_stack`
+    #[cfg_attr(feature = "serde", serde(default))]
+    strict_stack: bool,
+
+    /// pc wraps at the end of a memory smaller than 64K, see `set_pc_wrap`
+    #[cfg_attr(feature = "serde", serde(default))]
+    wrap_pc: bool,
+
+    /// addresses where `run` stops
+    breakpoints: HashSet<u16>,
+
+    /// data addresses where `run` stops after the instruction touching them
+    read_watchpoints: HashSet<u16>,
+    write_watchpoints: HashSet<u16>,
+
+    /// the last watchpoint an instruction triggered
+    #[cfg_attr(feature = "serde", serde(skip))]
+    watch_hit: Option<StopReason>,
+
+    /// the last data accesses, when enabled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    access_log: Option<AccessLog>,
+
+    /// where instructions began and ended, when checking jumps into operands
+    #[cfg_attr(feature = "serde", serde(skip))]
+    boundaries: Option<BoundaryCheck>,
+
+    /// called before every instruction executes, for logging
+    #[cfg_attr(feature = "serde", serde(skip))]
+    trace: Option<TraceHook>,
+
+    /// called after every instruction with the cycles it took
+    #[cfg_attr(feature = "serde", serde(skip))]
+    clock: Option<ClockSink>,
+
+    /// called when SED runs while decimal mode is turned off
+    #[cfg_attr(feature = "serde", serde(skip))]
+    decimal_warning: Option<DecimalWarning>,
+
+    /// called when a jump lands in the middle of an instruction
+    #[cfg_attr(feature = "serde", serde(skip))]
+    boundary_hook: Option<BoundaryHook>
+}
+
+/// forks the cpu and its memory, e.g. to try a few steps and throw them away.
+/// the callbacks can't be copied, so the clone starts without trace, clock or warning hooks
+impl<B: Bus + Clone> Clone for Cpu<B> {
+    fn clone(&self) -> Cpu<B> {
+        Cpu {
+            pc: self.pc,
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            sp: self.sp,
+            sr: self.sr,
+            memory: self.memory.clone(),
+            cycles_busy: self.cycles_busy,
+            cycles: self.cycles,
+            last_cycles: self.last_cycles,
+            ready: self.ready,
+            irq_line: self.irq_line,
+            delayed_interrupt_disable: self.delayed_interrupt_disable,
+            variant: self.variant,
+            binary_only: self.binary_only,
+            strict_stack: self.strict_stack,
+            wrap_pc: self.wrap_pc,
+            breakpoints: self.breakpoints.clone(),
+            read_watchpoints: self.read_watchpoints.clone(),
+            write_watchpoints: self.write_watchpoints.clone(),
+            watch_hit: self.watch_hit,
+            access_log: self.access_log.clone(),
+            boundaries: self.boundaries.clone(),
+            trace: None,
+            clock: None,
+            decimal_warning: None,
+            boundary_hook: None
+        }
+    }
+}
+
+/// which member of the 6502 family to behave like
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CpuVariant {
+    /// the original 6502, and the 6507 in the 2600, bugs included
+    #[default]
+    Nmos,
+    /// the 65C02, which fixed the JMP indirect bug among other things
+    Cmos
 }
 
-impl Cpu {
-    pub fn new(mem: Memory) -> Cpu {
+/// where a branch with `offset` goes, counting from `pc`, the address after the branch
+fn relative_target(pc : u16, offset : u8) -> u16 {
+    pc.wrapping_add(offset as i8 as u16)
+}
+
+/// snapshot of the registers
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CpuState {
+    pub pc: u16,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub sr: u8
+}
+
+impl<B: Bus> Cpu<B> {
+    /// starts at 0x1000, where the 2600 cartridge begins. see `new_from_reset` for a cpu
+    /// that starts where the reset vector says
+    pub fn new(mem: B) -> Cpu<B> {
         Cpu {
             sp: 0xff, // stack: [0x0100, 0x01ff] 
             pc: 0x1000, // cartridge first address
@@ -46,50 +209,276 @@ impl Cpu {
             y: 0,
             sr: 0,
             memory: mem,
-            cycles_busy: 0
+            cycles_busy: 0,
+            cycles: 0,
+            last_cycles: 0,
+            ready: true,
+            irq_line: false,
+            delayed_interrupt_disable: None,
+            variant: CpuVariant::Nmos,
+            binary_only: false,
+            strict_stack: false,
+            wrap_pc: false,
+            breakpoints: HashSet::new(),
+            read_watchpoints: HashSet::new(),
+            write_watchpoints: HashSet::new(),
+            watch_hit: None,
+            access_log: None,
+            boundaries: None,
+            trace: None,
+            clock: None,
+            decimal_warning: None,
+            boundary_hook: None
         }
     }
 
-    fn reset(&mut self) {
-        self.sp = 0xff;
-        self.pc = self.load_little_endian_u16(0xfffc).expect("Error: unexpected end of memory");
+    /// a cpu that went through reset, so it starts at the address in the reset vector (0xfffc)
+    pub fn new_from_reset(mem: B) -> Result<Cpu<B>, CpuError> {
+        let mut cpu = Cpu::new(mem);
+        cpu.reset()?;
+        Ok(cpu)
+    }
+
+    /// installs a callback that receives the address, the decoded instruction and the
+    /// registers right before each instruction executes
+    pub fn set_trace(&mut self, f: TraceHook) {
+        self.trace = Some(f);
+    }
+
+    /// installs a callback that receives the cycles each instruction took, once it finished.
+    /// whole instructions are reported for now; ticking peripherals on every cycle of an
+    /// instruction will need the cycles split where its bus accesses happen
+    pub fn set_clock_sink(&mut self, f: ClockSink) {
+        self.clock = Some(f);
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn memory(&self) -> &B {
+        &self.memory
+    }
+
+    /// for loading ROMs or patching RAM after the cpu was built
+    pub fn memory_mut(&mut self) -> &mut B {
+        &mut self.memory
+    }
+
+    pub fn variant(&self) -> CpuVariant {
+        self.variant
+    }
+
+    pub fn set_variant(&mut self, variant : CpuVariant) {
+        self.variant = variant;
+    }
+
+    pub fn decimal_supported(&self) -> bool {
+        !self.binary_only
+    }
+
+    /// turning decimal mode off keeps ADC and SBC binary whatever the D flag says, for ROMs
+    /// known not to use BCD. SED still sets the flag
+    pub fn set_decimal_supported(&mut self, supported : bool) {
+        self.binary_only = !supported;
+    }
+
+    /// with the strict stack on, pushing with sp at 0x00 or pulling with sp at 0xff is an error
+    /// instead of wrapping around like the hardware does. helps catching unbalanced JSR/RTS
+    pub fn set_strict_stack(&mut self, strict : bool) {
+        self.strict_stack = strict;
+    }
+
+    /// with the wrap on, pc goes back to 0 when it runs off the end of a memory smaller than 64K,
+    /// which is handy for tiny test loops. otherwise the next fetch fails with an
+    /// `OutOfRangeError`. 64K memories wrap around the 16-bit space either way, like the
+    /// hardware does whatever memory is installed
+    pub fn set_pc_wrap(&mut self, wrap : bool) {
+        self.wrap_pc = wrap;
+    }
+
+    /// installs a callback that receives the address of every SED run while decimal mode is off
+    pub fn set_decimal_warning(&mut self, f: DecimalWarning) {
+        self.decimal_warning = Some(f);
+    }
+
+    /// moves the program counter, e.g. to run code that doesn't start at the cartridge
+    pub fn set_pc(&mut self, addr : u16) {
+        self.pc = addr;
+    }
+
+    /// same as `set_pc`
+    pub fn jump_to(&mut self, addr : u16) {
+        self.set_pc(addr);
+    }
+
+    /// reads the accumulator
+    ///
+    /// ```
+    /// use ataritron::cpu::Cpu;
+    /// use ataritron::memory::Memory;
+    ///
+    /// let mut mem = Memory::new(64*1024).unwrap();
+    /// mem.load_rom(0x1000, &[0xa9, 0x42]).unwrap(); // LDA #$42
+    ///
+    /// let mut cpu = Cpu::new(mem);
+    /// cpu.step().unwrap();
+    /// assert_eq!(cpu.a(), 0x42);
+    /// ```
+    pub fn a(&self) -> u8 {
+        self.a
+    }
+
+    pub fn x(&self) -> u8 {
+        self.x
+    }
+
+    pub fn y(&self) -> u8 {
+        self.y
+    }
+
+    pub fn sp(&self) -> u8 {
+        self.sp
+    }
+
+    /// moves the stack pointer, e.g. to set up a stack frame before a test
+    pub fn set_sp(&mut self, sp : u8) {
+        self.sp = sp;
+    }
+
+    pub fn sr(&self) -> u8 {
+        self.status()
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// the cycles the last step took, with the page-cross and branch penalties
+    pub fn last_instruction_cycles(&self) -> u8 {
+        self.last_cycles
+    }
+
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            pc: self.pc,
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            sp: self.sp,
+            sr: self.status()
+        }
+    }
+
+    /// a cold boot: RAM, the registers, the chips on the bus and the interrupt and RDY lines
+    /// start from scratch, then the cpu goes through reset.
+    /// with `AddressMapping::Flat` every byte outside the `rom_region` counts as RAM, so a program
+    /// loaded there is wiped too. mark it with `Memory::set_rom_region` before powering on
+    pub fn power_on(&mut self) -> Result<(), CpuError> {
+        self.memory.clear_ram();
+        self.memory.reset_devices();
         self.a = 0;
         self.x = 0;
+        self.y = 0;
         self.sr = 0;
+        self.irq_line = false;
+        self.ready = true;
+        self.cycles = 0;
+        self.last_cycles = 0;
+        self.reset()
+    }
+
+    /// the reset line: RAM and the registers are kept, the cpu goes through the motions of an
+    /// interrupt without writing to the stack, disables interrupts and jumps through the reset vector.
+    /// the stack pointer ends at 0xfd, three fake pushes down from where the hardware starts,
+    /// which is what test ROMs expect.
+    /// decimal mode is cleared too, so arithmetic starts out binary. fails when the reset vector
+    /// isn't in memory. this is what the console's reset switch should trigger
+    pub fn reset(&mut self) -> Result<(), CpuError> {
+        self.pc = self.reset_vector()?;
+        self.sp = 0xfd;
+        self.set_flag(CpuFlags::InterruptDisable);
+        self.delayed_interrupt_disable = None;
+        self.clear_flag(CpuFlags::Decimal);
         self.cycles_busy = 1;
+        Ok(())
     }
 
+    /// reads a little-endian word. the high byte of a word at 0xffff comes from 0x0000, like
+    /// operands and vectors do on the hardware. the error names whichever byte is missing
     fn load_little_endian_u16(&self, addr : u16) -> Result<u16, OutOfRangeError> {
         let low_bytes = self.memory.load(addr)?;
-        let high_bytes = self.memory.load(addr + 1)?;
+        let high_bytes = self.memory.load(addr.wrapping_add(1))?;
+
+        Ok(((high_bytes as u16) << 8) | (low_bytes as u16))
+    }
+
+    /// indexes into the zeropage, which wraps around instead of moving to the next page
+    fn zp_addr(base : u8, offset : u8) -> u16 {
+        base.wrapping_add(offset) as u16
+    }
+
+    /// `load_little_endian_u16` without the side effects, for decoding
+    fn peek_little_endian_u16(&self, addr : u16) -> Result<u16, OutOfRangeError> {
+        let low_bytes = self.memory.peek(addr)?;
+        let high_bytes = self.memory.peek(addr.wrapping_add(1))?;
+
+        Ok(((high_bytes as u16) << 8) | (low_bytes as u16))
+    }
+
+    /// a load, or a peek when only `inspecting`
+    fn bus_read(&self, addr : u16, inspecting : bool) -> Result<u8, OutOfRangeError> {
+        if inspecting {
+            self.memory.peek(addr)
+        } else {
+            self.memory.load(addr)
+        }
+    }
+
+    /// reads a pointer kept in the zeropage. one at 0xff has its high byte at 0x00
+    fn load_zp_pointer(&self, addr : u8, inspecting : bool) -> Result<u16, OutOfRangeError> {
+        let low_bytes = self.bus_read(Self::zp_addr(addr, 0), inspecting)?;
+        let high_bytes = self.bus_read(Self::zp_addr(addr, 1), inspecting)?;
 
         Ok(((high_bytes as u16) << 8) | (low_bytes as u16))
     }
 
     fn get_effective_address(&self, addressing : Addressing) -> Result<u16, CpuError> {
+        self.effective_address(addressing, false)
+    }
+
+    /// where `addressing` points. pointers are only peeked when `inspecting`
+    fn effective_address(&self, addressing : Addressing, inspecting : bool) -> Result<u16, CpuError> {
         match addressing {
             Addressing::Absolute(addr) => Ok(addr),
-            Addressing::Zeropage(low_nibble) => Ok(0x0000 | low_nibble as u16),
-            Addressing::IndexedAbsolute(base, offset) => Ok(base + offset as u16),
-            Addressing::IndexedZeropage(low_nibble, offset) => Ok((0x0000 | low_nibble as u16) + offset as u16),
-            Addressing::PreindexedIndirect(low_nibble_base, offset) => Ok((0x0000 | low_nibble_base as u16) + offset as u16),
-            Addressing::PostindexedIndirect(low_nibble_base, offset) => {
-                let base_addr = self.load_little_endian_u16(0x0000 | low_nibble_base as u16).unwrap(); // should never panic
-                Ok(base_addr + offset as u16)
+            Addressing::Zeropage(low_nibble) => Ok(Self::zp_addr(low_nibble, 0)),
+            Addressing::IndexedAbsolute(base, offset) => Ok(base.wrapping_add(offset as u16)),
+            Addressing::IndexedZeropage(low_nibble, offset) => Ok(Self::zp_addr(low_nibble, offset)),
+            Addressing::PreindexedIndirect(low_nibble_base, offset) => {
+                Ok(self.load_zp_pointer(low_nibble_base.wrapping_add(offset), inspecting)?)
             },
-            Addressing::Indirect(addr) => self.load_little_endian_u16(addr).map_err(|e| CpuError::MemoryBoundsError(e)),
-            Addressing::RelativeAddress(offset) => {
-                if (offset & 0x80) != 0 {
-                    Ok(self.pc - (!offset as u16 + 1))
-                } else {
-                    Ok(self.pc + offset as u16)
-                }
+            Addressing::PostindexedIndirect(low_nibble_base, offset) => {
+                let base_addr = self.load_zp_pointer(low_nibble_base, inspecting)?;
+                Ok(base_addr.wrapping_add(offset as u16))
+            },
+            Addressing::Indirect(addr) => {
+                // the NMOS chips don't carry into the high byte of the pointer's address,
+                // so a pointer at 0x10ff is read from 0x10ff and 0x1000
+                let high_addr = match self.variant {
+                    CpuVariant::Nmos => (addr & 0xff00) | (addr.wrapping_add(1) & 0x00ff),
+                    CpuVariant::Cmos => addr.wrapping_add(1)
+                };
+                let low_bytes = self.bus_read(addr, inspecting)?;
+                let high_bytes = self.bus_read(high_addr, inspecting)?;
+
+                Ok(((high_bytes as u16) << 8) | (low_bytes as u16))
             },
+            Addressing::RelativeAddress(offset) => Ok(relative_target(self.pc, offset)),
             Addressing::Immediate(_) | Addressing::Implied => Err(CpuError::InvalidAddressModeDerefenced)
             }
     }
 
-    fn get_operand(&self, addressing : Addressing) -> Result<u8, CpuError> {
+    fn get_operand(&mut self, addressing : Addressing) -> Result<u8, CpuError> {
         match addressing {
             Addressing::Immediate(value) => Ok(value),
             Addressing::Absolute(_)
@@ -101,8 +490,8 @@ impl Cpu {
                 | Addressing::RelativeAddress(_)
             => {
                 let effective_addr = self.get_effective_address(addressing)?;
-                
-                self.memory.load(effective_addr).map_err(|e| CpuError::MemoryBoundsError(e))
+
+                self.read(effective_addr)
             },
             Addressing::Implied
                 | Addressing::Indirect(_)
@@ -111,1223 +500,389 @@ impl Cpu {
         }
     }
 
+    /// loads data for an instruction, checking the watchpoints and logging the access
+    fn read(&mut self, addr : u16) -> Result<u8, CpuError> {
+        let value = self.memory.load(addr)?;
+        if let Some(log) = self.access_log.as_mut() {
+            log.record(false, addr, value);
+        }
+        if self.read_watchpoints.contains(&addr) {
+            self.watch_hit = Some(StopReason::ReadWatchpoint { addr, value });
+        }
+
+        Ok(value)
+    }
+
+    /// stores data for an instruction, checking the watchpoints and logging the access
+    fn write(&mut self, addr : u16, value : u8) -> Result<(), CpuError> {
+        self.memory.store(addr, value)?;
+        if let Some(log) = self.access_log.as_mut() {
+            log.record(true, addr, value);
+        }
+        if self.write_watchpoints.contains(&addr) {
+            self.watch_hit = Some(StopReason::WriteWatchpoint { addr, value });
+        }
+
+        Ok(())
+    }
+
+    /// writes a register for STA, STX and STY. there's nowhere to store to with immediate
+    /// or implied operands, so a decoding mistake errors out instead of writing somewhere
+    fn store(&mut self, addressing : Addressing, value : u8) -> Result<(), CpuError> {
+        let addr = self.get_effective_address(addressing)?;
+        self.write(addr, value)
+    }
+
     /// fetches the next instruction to be run and increments the program counter
-    fn fetch(&mut self) -> Result<Instruction, OutOfRangeError> {
-        let opcode = self.memory.load(self.pc)?;
-        let instruction_size : u16;
-
-        let instruction = match opcode {
-            0x00 => {
-                instruction_size = 1;
-                Instruction { // BRK
-                    operation: Operations::SoftwareInterrupt,
-                    addressing: Addressing::Implied,
-                    cycle_count: 7
-                }
-            },
-            0x48 => {
-                instruction_size = 1;
-                Instruction { // PHA
-                    operation: Operations::PushAccumulator,
-                    addressing: Addressing::Implied,
-                    cycle_count: 3
-                }
-            },
-            0x68 => {
-                instruction_size = 1;
-                Instruction { // PLA
-                    operation: Operations::PullAccumulator,
-                    addressing: Addressing::Implied,
-                    cycle_count: 4
-                }
-            },
-            0x28 => {
-                instruction_size = 1;
-                Instruction { // PLP
-                    operation: Operations::PullStatusRegister,
-                    addressing: Addressing::Implied,
-                    cycle_count: 4
-                }
-            },
-            0x09 => {
-                instruction_size = 2;
-                Instruction {  // ORA immediate
-                    operation: Operations::InclusiveOrWithAccumulator,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0x05 => {
-                instruction_size = 2;
-                Instruction { // ORA zeropage
-                    operation: Operations::InclusiveOrWithAccumulator,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0x15 => {
-                instruction_size = 2;
-                Instruction { // ORA indexed zeropage
-                    operation: Operations::InclusiveOrWithAccumulator,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0x0d => {
-                instruction_size = 3;
-                Instruction { // ORA absolute
-                    operation: Operations::InclusiveOrWithAccumulator,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0x1d => {
-                instruction_size = 3;
-                Instruction { // ORA absolute,X
-                    operation: Operations::InclusiveOrWithAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0x19 => {
-                instruction_size = 3;
-                Instruction { // ORA absolute,Y
-                    operation: Operations::InclusiveOrWithAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.y),
-                    cycle_count: 4
-                }
-            },
-            0x01 => {
-                instruction_size = 2;
-                Instruction { // ORA (indirect,X)
-                    operation: Operations::InclusiveOrWithAccumulator,
-                    addressing: Addressing::PreindexedIndirect(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0x11 => {
-                instruction_size = 2;
-                Instruction { // ORA (indirect), Y
-                    operation: Operations::InclusiveOrWithAccumulator,
-                    addressing: Addressing::PostindexedIndirect(self.memory.load(self.pc + 1)?, self.y),
-                    cycle_count: 5
-                }
-            },
-            0x0a => {
-                instruction_size = 1;
-                Instruction { // ASL accumulator (implied)
-                    operation: Operations::ArithmeticShiftLeft,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x06 => {
-                instruction_size = 2;
-                Instruction { // ASL zeropage 
-                    operation: Operations::ArithmeticShiftLeft,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 5
-                }
-            },
-            0x16 => {
-                instruction_size = 2;
-                Instruction { /// ASL zeropage,X
-                    operation: Operations::ArithmeticShiftLeft,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0x0e => {
-                instruction_size = 3;
-                Instruction { // ASL absolute
-                    operation: Operations::ArithmeticShiftLeft,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 6
-                }
-            },
-            0x1e => {
-                instruction_size = 3;
-                Instruction { // ASL absolute, X
-                    operation: Operations::ArithmeticShiftLeft,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 7
-                }
-            },
-            0x08 => {
-                instruction_size = 1;
-                Instruction { // PHP implied
-                    operation: Operations::PushStatusRegister,
-                    addressing: Addressing::Implied,
-                    cycle_count: 3
-                }
-            },
-            0x10 => {
-                instruction_size = 2;
-                Instruction { // BPL relative
-                    operation: Operations::BranchOnPlus,
-                    addressing: Addressing::RelativeAddress(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0x18 => {
-                instruction_size = 1;
-                Instruction { // CLC implied
-                    operation: Operations::ClearCarry,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x20 => {
-                instruction_size = 3;
-                Instruction { // JSR absolute
-                    operation: Operations::JumpSubroutine,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 6
-                }
-            },
-            0x29 => {
-                instruction_size = 2;
-                Instruction { // AND immediate
-                    operation: Operations::AndWithAccumulator,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0x25 => {
-                instruction_size = 2;
-                Instruction { // AND zeropage
-                    operation: Operations::AndWithAccumulator,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0x35 => {
-                instruction_size = 2;
-                Instruction { // AND zeropage,X
-                    operation: Operations::AndWithAccumulator,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0x2d => {
-                instruction_size = 3;
-                Instruction { // AND absolute
-                    operation: Operations::AndWithAccumulator,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0x3d => {
-                instruction_size = 3;
-                Instruction { // AND absolute,X
-                    operation: Operations::AndWithAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0x39 => {
-                instruction_size = 3;
-                Instruction { // AND absolute,Y
-                    operation: Operations::AndWithAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.y),
-                    cycle_count: 4
-                }
-            },
-            0x21 => {
-                instruction_size = 2;
-                Instruction { // AND (indirect,X)
-                    operation: Operations::AndWithAccumulator,
-                    addressing: Addressing::PreindexedIndirect(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0x31 => {
-                instruction_size = 2;
-                Instruction { // AND (indirect),Y
-                    operation: Operations::AndWithAccumulator,
-                    addressing: Addressing::PostindexedIndirect(self.memory.load(self.pc + 1)?, self.y),
-                    cycle_count: 5
-                }
-            },
-            0x24 => {
-                instruction_size = 2;
-                Instruction { // BIT zeropage
-                    operation: Operations::BitTest,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0x2c => {
-                instruction_size = 3;
-                Instruction { // BIT absolute
-                    operation: Operations::BitTest,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0x2a => {
-                instruction_size = 1;
-                Instruction { // ROL accumulator
-                    operation: Operations::RotateLeft,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x26 => {
-                instruction_size = 2;
-                Instruction { // ROL zeropage
-                    operation: Operations::RotateLeft,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 5
-                }
-            },
-            0x36 => {
-                instruction_size = 2;
-                Instruction { // ROL zeropage, X
-                    operation: Operations::RotateLeft,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0x2e => {
-                instruction_size = 3;
-                Instruction { // ROL absolute
-                    operation: Operations::RotateLeft,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 6
-                }
-            },
-            0x3e => {
-                instruction_size = 3;
-                Instruction { // ROL absolute, X
-                    operation: Operations::RotateLeft,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 7
-                }
-            },
-            0x30 => {
-                instruction_size = 2;
-                Instruction { // BMI relative
-                    operation: Operations::BranchOnMinus,
-                    addressing: Addressing::RelativeAddress(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0x38 => {
-                instruction_size = 1;
-                Instruction { // SEC
-                    operation: Operations::SetCarry,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x40 => {
-                instruction_size = 1;
-                Instruction { // RTI 
-                    operation: Operations::ReturnFromInterrupt,
-                    addressing: Addressing::Implied,
-                    cycle_count: 6
-                }
-            },
-            0x49 => {
-                instruction_size = 2;
-                Instruction { // EOR immediate
-                    operation: Operations::ExclusiveOrWithAccumulator,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0x45 => {
-                instruction_size = 2;
-                Instruction { // EOR zeropage
-                    operation: Operations::ExclusiveOrWithAccumulator,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0x55 => {
-                instruction_size = 2;
-                Instruction { // EOR zeropage, X
-                    operation: Operations::ExclusiveOrWithAccumulator,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0x4d => {
-                instruction_size = 3;
-                Instruction { // EOR absolute
-                    operation: Operations::ExclusiveOrWithAccumulator,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0x5d => {
-                instruction_size = 3;
-                Instruction { // EOR absolute,X
-                    operation: Operations::ExclusiveOrWithAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0x59 => {
-                instruction_size = 3;
-                Instruction { // EOR absolute,Y
-                    operation: Operations::ExclusiveOrWithAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.y),
-                    cycle_count: 4
-                }
-            },
-            0x41 => {
-                instruction_size = 2;
-                Instruction { // EOR (indirect,X)
-                    operation: Operations::ExclusiveOrWithAccumulator,
-                    addressing: Addressing::PreindexedIndirect(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0x51 => {
-                instruction_size = 2;
-                Instruction { // EOR (indirect),Y
-                    operation: Operations::ExclusiveOrWithAccumulator,
-                    addressing: Addressing::PostindexedIndirect(self.memory.load(self.pc + 1)?, self.y),
-                    cycle_count: 5
-                }
-            },
-            0x4a => {
-                instruction_size = 1;
-                Instruction { // LSR accumulator
-                    operation: Operations::LogicalShiftRight,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x46 => {
-                instruction_size = 2;
-                Instruction { // LSR zeropage
-                    operation: Operations::LogicalShiftRight,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 5
-                }
-            },
-            0x56 => {
-                instruction_size = 2;
-                Instruction { // LSR zeropage,X
-                    operation: Operations::LogicalShiftRight,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0x4e => {
-                instruction_size = 3;
-                Instruction { // LSR absolute
-                    operation: Operations::LogicalShiftRight,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 6
-                }
-            },
-            0x5e => {
-                instruction_size = 3;
-                Instruction { // LSR absolute,X
-                    operation: Operations::LogicalShiftRight,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 7
-                }
-            },
-            0x4c => {
-                instruction_size = 3;
-                Instruction { // JMP absolute
-                    operation: Operations::Jump,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0x6c => {
-                instruction_size = 3;
-                Instruction { // JMP indirect
-                    operation: Operations::Jump,
-                    addressing: Addressing::Indirect(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 5
-                }
-            },
-            0x50 => {
-                instruction_size = 2;
-                Instruction { // BVC relative
-                    operation: Operations::BranchOnOverflowClear,
-                    addressing: Addressing::RelativeAddress(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0x58 => {
-                instruction_size = 1;
-                Instruction { // CLI
-                    operation: Operations::ClearInterruptDisable,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x60 => {
-                instruction_size = 1;
-                Instruction { // RTS
-                    operation: Operations::ReturnFromSubroutine,
-                    addressing: Addressing::Implied,
-                    cycle_count: 6
-                }
-            },
-            0x69 => {
-                instruction_size = 2;
-                Instruction { // ADC immediate
-                    operation: Operations::AddWithCarry,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0x65 => {
-                instruction_size = 2;
-                Instruction { // ADC zeropage
-                    operation: Operations::AddWithCarry,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0x75 => {
-                instruction_size = 2;
-                Instruction { // ADC zeropage,X
-                    operation: Operations::AddWithCarry,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0x6d => {
-                instruction_size = 3;
-                Instruction { // ADC absolute
-                    operation: Operations::AddWithCarry,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0x7d => {
-                instruction_size = 3;
-                Instruction { // ADC absolute,X
-                    operation: Operations::AddWithCarry,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0x79 => {
-                instruction_size = 3;
-                Instruction { // ADC absolute,Y
-                    operation: Operations::AddWithCarry,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.y),
-                    cycle_count: 4
-                }
-            },
-            0x61 => {
-                instruction_size = 2;
-                Instruction { // ADC (indirect,X)
-                    operation: Operations::AddWithCarry,
-                    addressing: Addressing::PreindexedIndirect(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0x71 => {
-                instruction_size = 2;
-                Instruction { // ADC (indirect),Y
-                    operation: Operations::AddWithCarry,
-                    addressing: Addressing::PostindexedIndirect(self.memory.load(self.pc + 1)?, self.y),
-                    cycle_count: 5
-                }
-            },
-            0x6a => {
-                instruction_size = 1;
-                Instruction { // ROR accumulator
-                    operation: Operations::RotateRight,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x66 => {
-                instruction_size = 2;
-                Instruction { // ROR zeropage
-                    operation: Operations::RotateRight,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 5
-                }
-            },
-            0x76 => {
-                instruction_size = 2;
-                Instruction { // ROR zeropage,X
-                    operation: Operations::RotateRight,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0x6e => {
-                instruction_size = 3;
-                Instruction { // ROR absolute
-                    operation: Operations::RotateRight,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 6
-                }
-            },
-            0x7e => {
-                instruction_size = 3;
-                Instruction { // ROR absolute,X
-                    operation: Operations::RotateRight,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 7
-                }
-            },
-            0x70 => {
-                instruction_size = 2;
-                Instruction { // BVS relative
-                    operation: Operations::BranchOnOverflowSet,
-                    addressing: Addressing::RelativeAddress(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0x78 => {
-                instruction_size = 1;
-                Instruction { // SEI
-                    operation: Operations::SetInterruptDisable,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x85 => {
-                instruction_size = 2;
-                Instruction { // STA zeropage
-                    operation: Operations::StoreAccumulator,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0x95 => {
-                instruction_size = 2;
-                Instruction { // STA zeropage,X
-                    operation: Operations::StoreAccumulator,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0x8d => {
-                instruction_size = 3;
-                Instruction { // STA absolute
-                    operation: Operations::StoreAccumulator,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0x9d => {
-                instruction_size = 3;
-                Instruction { // STA absolute,X
-                    operation: Operations::StoreAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 5
-                }
-            },
-            0x99 => {
-                instruction_size = 3;
-                Instruction { // STA absolute,Y
-                    operation: Operations::StoreAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.y),
-                    cycle_count: 5
-                }
-            },
-            0x81 => {
-                instruction_size = 2;
-                Instruction { // STA (indirect,X)
-                    operation: Operations::StoreAccumulator,
-                    addressing: Addressing::PreindexedIndirect(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0x91 => {
-                instruction_size = 2;
-                Instruction { // STA (indirect),Y
-                    operation: Operations::StoreAccumulator,
-                    addressing: Addressing::PostindexedIndirect(self.memory.load(self.pc + 1)?, self.y),
-                    cycle_count: 6
-                }
-            },
-            0x84 => {
-                instruction_size = 2;
-                Instruction { // STY zeropage
-                    operation: Operations::StoreY,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0x94 => {
-                instruction_size = 2;
-                Instruction { // STY zeropage,X
-                    operation: Operations::StoreY,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0x8c => {
-                instruction_size = 3;
-                Instruction { // STY absolute
-                    operation: Operations::StoreY,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0x86 => {
-                instruction_size = 2;
-                Instruction { // STX zeropage
-                    operation: Operations::StoreX,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0x96 => {
-                instruction_size = 2;
-                Instruction { // STX zeropage,Y
-                    operation: Operations::StoreX,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.y),
-                    cycle_count: 4
-                }
-            },
-            0x8e => {
-                instruction_size = 3;
-                Instruction { // STX absolute
-                    operation: Operations::StoreX,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0x88 => {
-                instruction_size = 1;
-                Instruction { // DEY
-                    operation: Operations::DecrementY,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x8a => {
-                instruction_size = 1;
-                Instruction { // TXA
-                    operation: Operations::TransferXToAccumulator,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x90 => {
-                instruction_size = 2;
-                Instruction { // BCC relative
-                    operation: Operations::BranchOnCarryClear,
-                    addressing: Addressing::RelativeAddress(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0x98 => {
-                instruction_size = 1;
-                Instruction { // TYA
-                    operation: Operations::TransferYToAccumulator,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0x9a => {
-                instruction_size = 1;
-                Instruction { // TXS
-                    operation: Operations::TransferXToStackPointer,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0xa0 => {
-                instruction_size = 2;
-                Instruction { // LDY immediate
-                    operation: Operations::LoadY,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0xa4 => {
-                instruction_size = 2;
-                Instruction { // LDY zeropage
-                    operation: Operations::LoadY,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0xb4 => {
-                instruction_size = 2;
-                Instruction { // LDY zeropage,X
-                    operation: Operations::LoadY,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0xac => {
-                instruction_size = 3;
-                Instruction { // LDY absolute
-                    operation: Operations::LoadY,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0xbc => {
-                instruction_size = 3;
-                Instruction { // LDY absolute,X
-                    operation: Operations::LoadY,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0xa2 => {
-                instruction_size = 2;
-                Instruction { // LDX immediate
-                    operation: Operations::LoadX,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0xa6 => {
-                instruction_size = 2;
-                Instruction { // LDX zeropage
-                    operation: Operations::LoadX,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0xb6 => {
-                instruction_size = 2;
-                Instruction { // LDX zeropage,Y
-                    operation: Operations::LoadX,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.y),
-                    cycle_count: 4
-                }
-            },
-            0xae => {
-                instruction_size = 3;
-                Instruction { // LDX absolute
-                    operation: Operations::LoadX,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0xbe => {
-                instruction_size = 3;
-                Instruction { // LDX absolute,Y
-                    operation: Operations::LoadX,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.y),
-                    cycle_count: 4
-                }
-            },
-            0xa9 => {
-                instruction_size = 2;
-                Instruction { // LDA immediate
-                    operation: Operations::LoadAccumulator,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0xa5 => {
-                instruction_size = 2;
-                Instruction { // LDA zeropage
-                    operation: Operations::LoadAccumulator,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0xb5 => {
-                instruction_size = 2;
-                Instruction { // LDA zeropage,X
-                    operation: Operations::LoadAccumulator,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0xad => {
-                instruction_size = 3;
-                Instruction { // LDA absolute
-                    operation: Operations::LoadAccumulator,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0xbd => {
-                instruction_size = 3;
-                Instruction { // LDA absolute,X
-                    operation: Operations::LoadAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0xb9 => {
-                instruction_size = 3;
-                Instruction { // LDA absolute,Y
-                    operation: Operations::LoadAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.y),
-                    cycle_count: 4
-                }
-            },
-            0xa1 => {
-                instruction_size = 2;
-                Instruction { // LDA (indirect,X)
-                    operation: Operations::LoadAccumulator,
-                    addressing: Addressing::PreindexedIndirect(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0xb1 => {
-                instruction_size = 2;
-                Instruction { // LDA (indirect), Y
-                    operation: Operations::LoadAccumulator,
-                    addressing: Addressing::PostindexedIndirect(self.memory.load(self.pc + 1)?, self.y),
-                    cycle_count: 5
-                }
-            },
-            0xa8 => {
-                instruction_size = 1;
-                Instruction { // TAY
-                    operation: Operations::TransferAccumulatorToY,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0xaa => {
-                instruction_size = 1;
-                Instruction { // TAX
-                    operation: Operations::TransferAccumulatorToX,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0xb0 => {
-                instruction_size = 2;
-                Instruction { // BCS
-                    operation: Operations::BranchOnCarrySet,
-                    addressing: Addressing::RelativeAddress(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0xb8 => {
-                instruction_size = 1;
-                Instruction { // CLV
-                    operation: Operations::ClearOverflow,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0xba => {
-                instruction_size = 1;
-                Instruction { // TSX
-                    operation: Operations::TransferStackPointerToX,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0xc0 => {
-                instruction_size = 2;
-                Instruction { // CPY immediate
-                    operation: Operations::CompareWithY,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0xc4 => {
-                instruction_size = 2;
-                Instruction { // CPY zeropage
-                    operation: Operations::CompareWithY,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0xcc => {
-                instruction_size = 3;
-                Instruction { // CPY absolute
-                    operation: Operations::CompareWithY,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0xc9 => {
-                instruction_size = 2;
-                Instruction { // CMP immediate
-                    operation: Operations::CompareWithAccumulator,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0xc5 => {
-                instruction_size = 2;
-                Instruction { // CMP zeropage
-                    operation: Operations::CompareWithAccumulator,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0xd5 => {
-                instruction_size = 2;
-                Instruction { // CMP zeropage,X
-                    operation: Operations::CompareWithAccumulator,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0xcd => {
-                instruction_size = 3;
-                Instruction { // CMP absolute
-                    operation: Operations::CompareWithAccumulator,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0xdd => {
-                instruction_size = 3;
-                Instruction { // CMP absolute,X
-                    operation: Operations::CompareWithAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0xd9 => {
-                instruction_size = 3;
-                Instruction { // CMP absolute, Y
-                    operation: Operations::CompareWithAccumulator,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.y),
-                    cycle_count: 4
-                }
-            },
-            0xc1 => {
-                instruction_size = 2;
-                Instruction { // CMP (indirect,X)
-                    operation: Operations::CompareWithAccumulator,
-                    addressing: Addressing::PreindexedIndirect(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0xd1 => {
-                instruction_size = 2;
-                Instruction { // CMP (indirect),Y
-                    operation: Operations::CompareWithAccumulator,
-                    addressing: Addressing::PostindexedIndirect(self.memory.load(self.pc + 1)?, self.y),
-                    cycle_count: 5
-                }
-            },
-            0xc6 => {
-                instruction_size = 2;
-                Instruction { // DEC zeropage
-                    operation: Operations::DecrementMemory,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 5
-                }
-            },
-            0xd6 => {
-                instruction_size = 2;
-                Instruction { // DEC zeropage,X
-                    operation: Operations::DecrementMemory,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0xce => {
-                instruction_size = 3;
-                Instruction { // DEC absolute
-                    operation: Operations::DecrementMemory,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 6
-                }
-            },
-            0xde => {
-                instruction_size = 3;
-                Instruction { // DEC absolute,X
-                    operation: Operations::DecrementMemory,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 7
-                }
-            },
-            0xc8 => {
-                instruction_size = 1;
-                Instruction { // INY
-                    operation: Operations::IncrementY,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0xca => {
-                instruction_size = 1;
-                Instruction { // DEX
-                    operation: Operations::DecrementX,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0xd0 => {
-                instruction_size = 2;
-                Instruction { // BNE
-                    operation: Operations::BranchOnNotEqual,
-                    addressing: Addressing::RelativeAddress(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0xd8 => {
-                instruction_size = 1;
-                Instruction { // CLD
-                    operation: Operations::ClearDecimal,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0xe0 => {
-                instruction_size = 2;
-                Instruction { // CPX immediate
-                    operation: Operations::CompareWithX,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0xe4 => {
-                instruction_size = 2;
-                Instruction { // CPX zeropage
-                    operation: Operations::CompareWithX,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0xec => {
-                instruction_size = 3;
-                Instruction { // CPX absolute
-                    operation: Operations::CompareWithX,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0xe9 => {
-                instruction_size = 2;
-                Instruction { // SBC immediate
-                    operation: Operations::SubtractWithCarry,
-                    addressing: Addressing::Immediate(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0xe5 => {
-                instruction_size = 2;
-                Instruction { // SBC zeropage
-                    operation: Operations::SubtractWithCarry,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 3
-                }
-            },
-            0xf5 => {
-                instruction_size = 2;
-                Instruction { // SBC zeropage,X
-                    operation: Operations::SubtractWithCarry,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0xed => {
-                instruction_size = 3;
-                Instruction { // SBC absolute
-                    operation: Operations::SubtractWithCarry,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 4
-                }
-            },
-            0xfd => {
-                instruction_size = 3;
-                Instruction { // SBC absolute,X
-                    operation: Operations::SubtractWithCarry,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 4
-                }
-            },
-            0xf9 => {
-                instruction_size = 3;
-                Instruction { // SBC absolute,Y
-                    operation: Operations::SubtractWithCarry,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.y),
-                    cycle_count: 4
-                }
-            },
-            0xe1 => {
-                instruction_size = 2;
-                Instruction { // SBC (indirect,X)
-                    operation: Operations::SubtractWithCarry,
-                    addressing: Addressing::PreindexedIndirect(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0xf1 => {
-                instruction_size = 2;
-                Instruction { // SBC (indirect),Y
-                    operation: Operations::SubtractWithCarry,
-                    addressing: Addressing::PostindexedIndirect(self.memory.load(self.pc + 1)?, self.y),
-                    cycle_count: 5
-                }
-            },
-            0xe6 => {
-                instruction_size = 2;
-                Instruction { // INC zeropage
-                    operation: Operations::IncrementMemory,
-                    addressing: Addressing::Zeropage(self.memory.load(self.pc + 1)?),
-                    cycle_count: 5
-                }
-            },
-            0xf6 => {
-                instruction_size = 2;
-                Instruction { // INC zeropage,X
-                    operation: Operations::IncrementMemory,
-                    addressing: Addressing::IndexedZeropage(self.memory.load(self.pc + 1)?, self.x),
-                    cycle_count: 6
-                }
-            },
-            0xee => {
-                instruction_size = 3;
-                Instruction { // INC absolute
-                    operation: Operations::IncrementMemory,
-                    addressing: Addressing::Absolute(self.load_little_endian_u16(self.pc + 1)?),
-                    cycle_count: 6
-                }
-            },
-            0xfe => {
-                instruction_size = 3;
-                Instruction { // INC absolute,X
-                    operation: Operations::IncrementMemory,
-                    addressing: Addressing::IndexedAbsolute(self.load_little_endian_u16(self.pc + 1)?, self.x),
-                    cycle_count: 7
-                }
-            },
-            0xe8 => {
-                instruction_size = 1;
-                Instruction { // INX
-                    operation: Operations::IncrementX,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0xf0 => {
-                instruction_size = 2;
-                Instruction { // BEQ relative
-                    operation: Operations::BranchOnEqual,
-                    addressing: Addressing::RelativeAddress(self.memory.load(self.pc + 1)?),
-                    cycle_count: 2
-                }
-            },
-            0xf8 => {
-                instruction_size = 1;
-                Instruction { // SED
-                    operation: Operations::SetDecimal,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            },
-            0xea | _ => {
-                instruction_size = 1;
-                Instruction {
-                    operation: Operations::NoOperation,
-                    addressing: Addressing::Implied,
-                    cycle_count: 2
-                }
-            }
-        };
+    fn fetch(&mut self) -> Result<Instruction, CpuError> {
+        // decoding only peeks, so the bus still has to see the bytes being read. the opcode
+        // goes first in case reading it switches banks under the operands
+        self.memory.load(self.pc)?;
+        let (instruction, instruction_size) = self.decode_at(self.pc)?;
+        for offset in 1..instruction_size {
+            self.memory.load(self.pc.wrapping_add(offset))?;
+        }
 
-        self.pc += instruction_size;
+        self.pc = self.pc.wrapping_add(instruction_size);
+        if self.wrap_pc {
+            self.pc = (self.pc as u32 % self.memory.address_space()) as u16;
+        }
         Ok(instruction)
     }
+
+    /// cycles left before the running instruction is done
+    pub fn busy_cycles(&self) -> u8 {
+        self.cycles_busy
+    }
+
+    /// advances a single cycle, for drivers that clock the cpu cycle by cycle.
+    /// the whole instruction runs on its first cycle, then the cpu sits out the rest
+    pub fn tick(&mut self) -> Result<(), CpuError> {
+        if self.cycles_busy == 0 {
+            self.cycles_busy = self.step()?;
+        }
+
+        self.cycles_busy -= 1;
+        Ok(())
+    }
+
+    /// pulling RDY low halts the cpu until it's raised again, like the TIA does on WSYNC
+    pub fn set_rdy(&mut self, ready : bool) {
+        self.ready = ready;
+    }
+
+    /// raises or lowers the IRQ line. while it's up and interrupts are enabled, the next step
+    /// enters the handler instead of running an instruction
+    pub fn set_irq_line(&mut self, asserted : bool) {
+        self.irq_line = asserted;
+    }
+
+    /// whether RDY is high, both from `set_rdy` and the devices on the bus
+    fn is_ready(&self) -> bool {
+        self.ready && self.memory.ready()
+    }
+
+    /// fetches and executes the next instruction, or enters the IRQ handler when the line is up,
+    /// returning the cycles it took.
+    /// while RDY is low nothing runs and every step takes a single cycle
+    pub fn step(&mut self) -> Result<u8, CpuError> {
+        if !self.is_ready() {
+            self.consume(1);
+            return Ok(1);
+        }
+
+        if let Some(cycles) = self.poll_irq()? {
+            return Ok(cycles);
+        }
+
+        let state = self.state();
+        let instruction = self.fetch()?;
+
+        if let Some(trace) = self.trace.as_mut() {
+            trace(state.pc, &instruction, &state);
+        }
+
+        let next = self.pc;
+        let operation = instruction.operation;
+        let cycles = instruction.cycle_count as u8 + self.execute(instruction)?;
+        self.delay_interrupt_disable(operation, state.sr);
+        self.check_boundaries(state.pc, next);
+        self.consume(cycles);
+        Ok(cycles)
+    }
+
+    /// writes `opcode` and its `operands` at pc and steps over them, returning the cycles.
+    /// saves assembling a ROM to try a single instruction
+    pub fn run_opcode(&mut self, opcode : u8, operands : &[u8]) -> Result<u8, CpuError> {
+        let mut addr = self.pc;
+        for &byte in std::iter::once(&opcode).chain(operands) {
+            self.memory.store(addr, byte)?;
+            addr = addr.wrapping_add(1);
+        }
+
+        self.step()
+    }
+
+    /// accounts for cycles spent, letting the bus and the clock sink catch up
+    fn consume(&mut self, cycles : u8) {
+        self.last_cycles = cycles;
+        self.cycles += cycles as u64;
+        self.memory.tick(cycles);
+        if let Some(clock) = self.clock.as_mut() {
+            clock(cycles as u64);
+        }
+    }
+
+    /// whether indexing moved the effective address into another page. the pointer is only
+    /// peeked, the instruction itself does the real read
+    fn crosses_page(&self, addressing : &Addressing) -> Result<bool, CpuError> {
+        let (base, offset) = match *addressing {
+            Addressing::IndexedAbsolute(base, offset) => (base, offset),
+            Addressing::PostindexedIndirect(low_nibble_base, offset) => (self.load_zp_pointer(low_nibble_base, true)?, offset),
+            _ => return Ok(false)
+        };
+
+        Ok(base & 0xff00 != base.wrapping_add(offset as u16) & 0xff00)
+    }
+
+    /// runs `modify` over the accumulator (implied addressing) or a byte in memory, then sets
+    /// N and Z from the result. like the NMOS 6502, memory gets the unmodified byte written back
+    /// before the result, which strobe registers notice
+    fn read_modify_write(&mut self, addressing : Addressing, modify : impl FnOnce(&mut Self, u8) -> u8) -> Result<(), CpuError> {
+        let result = if addressing == Addressing::Implied {
+            self.a = modify(self, self.a);
+            self.a
+        } else {
+            let addr = self.get_effective_address(addressing)?;
+            let value = self.read(addr)?;
+            self.write(addr, value)?;
+            let result = modify(self, value);
+            self.write(addr, result)?;
+            result
+        };
+
+        self.set_nz(result);
+        Ok(())
+    }
+
+    /// jumps to a branch target when `condition` holds. returns the extra cycles: one for
+    /// taking the branch, one more when the target is on another page
+    fn branch(&mut self, addressing : Addressing, condition : bool) -> Result<u8, CpuError> {
+        if !condition {
+            return Ok(0);
+        }
+
+        let target = self.get_effective_address(addressing)?;
+        let extra_cycles = if target & 0xff00 != self.pc & 0xff00 { 2 } else { 1 };
+        self.pc = target;
+        Ok(extra_cycles)
+    }
+
+    /// runs a decoded instruction, returning the cycles it took on top of its base cycle count.
+    /// reads pay one more cycle when indexing crosses a page; writes and read-modify-writes
+    /// always spend it, so it's in their base count
+    fn execute(&mut self, instruction : Instruction) -> Result<u8, CpuError> {
+        let mut extra_cycles = 0;
+
+        match instruction.operation {
+            Operations::LoadAccumulator => {
+                extra_cycles += self.crosses_page(&instruction.addressing)? as u8;
+                self.a = self.get_operand(instruction.addressing)?;
+                self.set_nz(self.a);
+            },
+            Operations::LoadX => {
+                extra_cycles += self.crosses_page(&instruction.addressing)? as u8;
+                self.x = self.get_operand(instruction.addressing)?;
+                self.set_nz(self.x);
+            },
+            Operations::LoadY => {
+                extra_cycles += self.crosses_page(&instruction.addressing)? as u8;
+                self.y = self.get_operand(instruction.addressing)?;
+                self.set_nz(self.y);
+            },
+            Operations::IncrementMemory => self.read_modify_write(instruction.addressing, |_, value| value.wrapping_add(1))?,
+            Operations::DecrementMemory => self.read_modify_write(instruction.addressing, |_, value| value.wrapping_sub(1))?,
+            Operations::IncrementX => {
+                self.x = self.x.wrapping_add(1);
+                self.set_nz(self.x);
+            },
+            Operations::IncrementY => {
+                self.y = self.y.wrapping_add(1);
+                self.set_nz(self.y);
+            },
+            Operations::DecrementX => {
+                self.x = self.x.wrapping_sub(1);
+                self.set_nz(self.x);
+            },
+            Operations::DecrementY => {
+                self.y = self.y.wrapping_sub(1);
+                self.set_nz(self.y);
+            },
+            Operations::TransferAccumulatorToX => {
+                self.x = self.a;
+                self.set_nz(self.x);
+            },
+            Operations::TransferAccumulatorToY => {
+                self.y = self.a;
+                self.set_nz(self.y);
+            },
+            Operations::TransferXToAccumulator => {
+                self.a = self.x;
+                self.set_nz(self.a);
+            },
+            Operations::TransferYToAccumulator => {
+                self.a = self.y;
+                self.set_nz(self.a);
+            },
+            Operations::TransferStackPointerToX => {
+                self.x = self.sp;
+                self.set_nz(self.x);
+            },
+            // the only transfer that leaves the flags alone
+            Operations::TransferXToStackPointer => self.sp = self.x,
+            Operations::ArithmeticShiftLeft => self.read_modify_write(instruction.addressing, |cpu, value| {
+                cpu.set_flag_to(CpuFlags::Carry, value & 0x80 != 0);
+                value << 1
+            })?,
+            Operations::LogicalShiftRight => self.read_modify_write(instruction.addressing, |cpu, value| {
+                cpu.set_flag_to(CpuFlags::Carry, value & 0x01 != 0);
+                value >> 1
+            })?,
+            Operations::RotateLeft => self.read_modify_write(instruction.addressing, |cpu, value| {
+                let carry = cpu.get_flag(CpuFlags::Carry) as u8;
+                cpu.set_flag_to(CpuFlags::Carry, value & 0x80 != 0);
+                value << 1 | carry
+            })?,
+            Operations::RotateRight => self.read_modify_write(instruction.addressing, |cpu, value| {
+                let carry = cpu.get_flag(CpuFlags::Carry) as u8;
+                cpu.set_flag_to(CpuFlags::Carry, value & 0x01 != 0);
+                value >> 1 | carry << 7
+            })?,
+            Operations::AddWithCarry => {
+                extra_cycles += self.crosses_page(&instruction.addressing)? as u8;
+                let value = self.get_operand(instruction.addressing)?;
+                self.add_with_carry(value);
+            },
+            Operations::AndWithAccumulator => {
+                extra_cycles += self.crosses_page(&instruction.addressing)? as u8;
+                self.a &= self.get_operand(instruction.addressing)?;
+                self.set_nz(self.a);
+            },
+            Operations::InclusiveOrWithAccumulator => {
+                extra_cycles += self.crosses_page(&instruction.addressing)? as u8;
+                self.a |= self.get_operand(instruction.addressing)?;
+                self.set_nz(self.a);
+            },
+            Operations::ExclusiveOrWithAccumulator => {
+                extra_cycles += self.crosses_page(&instruction.addressing)? as u8;
+                self.a ^= self.get_operand(instruction.addressing)?;
+                self.set_nz(self.a);
+            },
+            Operations::SubtractWithCarry => {
+                extra_cycles += self.crosses_page(&instruction.addressing)? as u8;
+                let value = self.get_operand(instruction.addressing)?;
+                self.subtract_with_carry(value);
+            },
+            Operations::BitTest => {
+                // N and V are copied from the operand, only Z looks at the AND
+                let value = self.get_operand(instruction.addressing)?;
+                self.set_flag_to(CpuFlags::Zero, self.a & value == 0);
+                self.set_flag_to(CpuFlags::Negative, value & 0x80 != 0);
+                self.set_flag_to(CpuFlags::Overflow, value & 0x40 != 0);
+            },
+            Operations::CompareWithAccumulator => {
+                extra_cycles += self.crosses_page(&instruction.addressing)? as u8;
+                let value = self.get_operand(instruction.addressing)?;
+                self.compare(self.a, value);
+            },
+            Operations::CompareWithX => {
+                let value = self.get_operand(instruction.addressing)?;
+                self.compare(self.x, value);
+            },
+            Operations::CompareWithY => {
+                let value = self.get_operand(instruction.addressing)?;
+                self.compare(self.y, value);
+            },
+            Operations::StoreAccumulator => self.store(instruction.addressing, self.a)?,
+            Operations::StoreX => self.store(instruction.addressing, self.x)?,
+            Operations::StoreY => self.store(instruction.addressing, self.y)?,
+            Operations::ClearCarry => self.clear_flag(CpuFlags::Carry),
+            Operations::ClearDecimal => self.clear_flag(CpuFlags::Decimal),
+            Operations::ClearInterruptDisable => self.clear_flag(CpuFlags::InterruptDisable),
+            Operations::ClearOverflow => self.clear_flag(CpuFlags::Overflow),
+            Operations::SetCarry => self.set_flag(CpuFlags::Carry),
+            Operations::SetDecimal => {
+                self.set_flag(CpuFlags::Decimal);
+                if self.binary_only {
+                    let addr = self.pc.wrapping_sub(1);
+                    if let Some(warn) = self.decimal_warning.as_mut() {
+                        warn(addr);
+                    }
+                }
+            },
+            Operations::SetInterruptDisable => self.set_flag(CpuFlags::InterruptDisable),
+            Operations::Jump => self.pc = self.get_effective_address(instruction.addressing)?,
+            Operations::BranchOnCarryClear => extra_cycles += self.branch(instruction.addressing, !self.get_flag(CpuFlags::Carry))?,
+            Operations::BranchOnCarrySet => extra_cycles += self.branch(instruction.addressing, self.get_flag(CpuFlags::Carry))?,
+            Operations::BranchOnNotEqual => extra_cycles += self.branch(instruction.addressing, !self.get_flag(CpuFlags::Zero))?,
+            Operations::BranchOnEqual => extra_cycles += self.branch(instruction.addressing, self.get_flag(CpuFlags::Zero))?,
+            Operations::BranchOnPlus => extra_cycles += self.branch(instruction.addressing, !self.get_flag(CpuFlags::Negative))?,
+            Operations::BranchOnMinus => extra_cycles += self.branch(instruction.addressing, self.get_flag(CpuFlags::Negative))?,
+            Operations::BranchOnOverflowClear => extra_cycles += self.branch(instruction.addressing, !self.get_flag(CpuFlags::Overflow))?,
+            Operations::BranchOnOverflowSet => extra_cycles += self.branch(instruction.addressing, self.get_flag(CpuFlags::Overflow))?,
+            Operations::SoftwareInterrupt => self.software_interrupt()?,
+            Operations::ReturnFromInterrupt => self.return_from_interrupt()?,
+            Operations::JumpSubroutine => {
+                let target = self.get_effective_address(instruction.addressing)?;
+                self.jump_to_subroutine(target)?;
+            },
+            Operations::ReturnFromSubroutine => self.return_from_subroutine()?,
+            Operations::PushAccumulator => self.push(self.a)?,
+            Operations::PullAccumulator => {
+                self.a = self.pull()?;
+                self.set_nz(self.a);
+            },
+            Operations::PushStatusRegister => self.push_status()?,
+            Operations::PullStatusRegister => self.pull_status()?,
+            Operations::NoOperation => {}
+        }
+
+        Ok(extra_cycles)
+    }
+
+    /// decodes the instruction starting at `addr` without touching the program counter.
+    /// memory is only peeked, so bank switches and the like don't happen.
+    /// returns the instruction and its size in bytes
+    pub fn decode_at(&self, addr : u16) -> Result<(Instruction, u16), CpuError> {
+        let opcode = self.memory.peek(addr)?;
+
+        self.decode_operands(opcode, addr).map_err(|e| match e {
+            CpuError::MemoryBoundsError(_) => CpuError::IncompleteInstruction { opcode, at: addr },
+            e => e
+        })
+    }
+
+    /// decodes an instruction whose opcode was already read from `addr`, going by `opcode_info`
+    fn decode_operands(&self, opcode : u8, addr : u16) -> Result<(Instruction, u16), CpuError> {
+        let Some(info) = opcode_info(opcode) else {
+            return Err(CpuError::IllegalOpcode { opcode, at: addr });
+        };
+        let operand = addr.wrapping_add(1);
+        let index = if index_register(opcode) == 'Y' { self.y } else { self.x };
+
+        let addressing = match info.mode {
+            AddressingMode::Implied => Addressing::Implied,
+            AddressingMode::Immediate => Addressing::Immediate(self.memory.peek(operand)?),
+            AddressingMode::Zeropage => Addressing::Zeropage(self.memory.peek(operand)?),
+            AddressingMode::IndexedZeropage => Addressing::IndexedZeropage(self.memory.peek(operand)?, index),
+            AddressingMode::Absolute => Addressing::Absolute(self.peek_little_endian_u16(operand)?),
+            AddressingMode::IndexedAbsolute => Addressing::IndexedAbsolute(self.peek_little_endian_u16(operand)?, index),
+            AddressingMode::Indirect => Addressing::Indirect(self.peek_little_endian_u16(operand)?),
+            AddressingMode::PreindexedIndirect => Addressing::PreindexedIndirect(self.memory.peek(operand)?, self.x),
+            AddressingMode::PostindexedIndirect => Addressing::PostindexedIndirect(self.memory.peek(operand)?, self.y),
+            AddressingMode::RelativeAddress => Addressing::RelativeAddress(self.memory.peek(operand)?)
+        };
+
+        Ok((Instruction { operation: info.operation, addressing, cycle_count: info.cycles }, info.bytes))
+    }
 }
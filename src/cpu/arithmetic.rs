@@ -0,0 +1,282 @@
+use super::*;
+
+impl<B: Bus> Cpu<B> {
+    /// adds `value` and the carry to the accumulator, setting C, V, N and Z
+    pub(super) fn add_with_carry(&mut self, value : u8) {
+        if self.decimal_mode() {
+            self.add_decimal(value);
+        } else {
+            self.add_binary(value);
+        }
+    }
+
+    /// subtracts `value` and the borrow (a clear carry) from the accumulator. in binary that's
+    /// adding the one's complement, which gives C and V for free
+    pub(super) fn subtract_with_carry(&mut self, value : u8) {
+        if self.decimal_mode() {
+            self.subtract_decimal(value);
+        } else {
+            self.add_binary(!value);
+        }
+    }
+
+    fn decimal_mode(&self) -> bool {
+        self.get_flag(CpuFlags::Decimal) && !self.binary_only
+    }
+
+    fn add_binary(&mut self, value : u8) {
+        let sum = self.a as u16 + value as u16 + self.get_flag(CpuFlags::Carry) as u16;
+        let result = sum as u8;
+
+        self.set_flag_to(CpuFlags::Carry, sum > 0xff);
+        // overflow when both operands have the same sign and the result doesn't
+        self.set_flag_to(CpuFlags::Overflow, !(self.a ^ value) & (self.a ^ result) & 0x80 != 0);
+        self.a = result;
+        self.set_nz(result);
+    }
+
+    /// BCD subtraction as the NMOS parts do it: the flags are the binary ones, only the
+    /// accumulator gets the digits adjusted
+    fn subtract_decimal(&mut self, value : u8) {
+        let a = self.a;
+        let borrow = 1 - self.get_flag(CpuFlags::Carry) as i16;
+        self.add_binary(!value);
+
+        let mut low = (a & 0x0f) as i16 - (value & 0x0f) as i16 - borrow;
+        if low < 0 {
+            low = ((low - 0x06) & 0x0f) - 0x10;
+        }
+        let mut difference = (a & 0xf0) as i16 - (value & 0xf0) as i16 + low;
+        if difference < 0 {
+            difference -= 0x60;
+        }
+        self.a = difference as u8;
+    }
+
+    /// CMP, CPX and CPY: a subtraction that only keeps the flags. C is set when `register`
+    /// is at least `value`, N comes from bit 7 of the wrapped difference
+    pub(super) fn compare(&mut self, register : u8, value : u8) {
+        self.set_flag_to(CpuFlags::Carry, register >= value);
+        self.set_nz(register.wrapping_sub(value));
+    }
+
+    /// BCD addition as the NMOS parts do it: Z comes from the binary sum, N and V from
+    /// the sum before the high digit is adjusted
+    fn add_decimal(&mut self, value : u8) {
+        let carry = self.get_flag(CpuFlags::Carry) as u16;
+
+        let mut low = (self.a & 0x0f) as u16 + (value & 0x0f) as u16 + carry;
+        if low > 0x09 {
+            low = ((low + 0x06) & 0x0f) + 0x10;
+        }
+        let mut sum = (self.a & 0xf0) as u16 + (value & 0xf0) as u16 + low;
+
+        self.set_flag_to(CpuFlags::Zero, self.a.wrapping_add(value).wrapping_add(carry as u8) == 0);
+        self.set_flag_to(CpuFlags::Negative, sum & 0x80 != 0);
+        self.set_flag_to(CpuFlags::Overflow, !(self.a ^ value) & (self.a ^ sum as u8) & 0x80 != 0);
+
+        if sum > 0x9f {
+            sum += 0x60;
+        }
+        self.set_flag_to(CpuFlags::Carry, sum > 0xff);
+        self.a = sum as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn adc_overflow_matches_the_signed_result() {
+        // a, operand, overflow
+        let cases = [
+            (0x50, 0x10, false),
+            (0x50, 0x50, true),
+            (0x50, 0x90, false),
+            (0x50, 0xd0, false),
+            (0xd0, 0x10, false),
+            (0xd0, 0x50, false),
+            (0xd0, 0x90, true),
+            (0xd0, 0xd0, false),
+        ];
+
+        for (a, operand, overflow) in cases {
+            let mut mem = Memory::new(64*1024).unwrap();
+            mem.load_rom(0x1000, &[0x69, operand]).unwrap(); // ADC #operand
+            let mut cpu = Cpu::new(mem);
+            cpu.a = a;
+
+            cpu.step().unwrap();
+            assert_eq!(a.wrapping_add(operand), cpu.a);
+            assert_eq!(overflow, cpu.get_flag(CpuFlags::Overflow), "{:02x} + {:02x}", a, operand);
+            assert_eq!(a as u16 + operand as u16 > 0xff, cpu.get_flag(CpuFlags::Carry), "{:02x} + {:02x}", a, operand);
+        }
+    }
+
+    #[test]
+    fn adc_adds_the_carry() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &[0x65, 0x10]).unwrap(); // ADC $10
+        mem.load_rom(0x0010, &[0xfe]).unwrap();
+        let mut cpu = Cpu::new(mem);
+        cpu.a = 0x01;
+        cpu.set_flag(CpuFlags::Carry);
+
+        cpu.step().unwrap();
+        assert_eq!(0x00, cpu.a);
+        assert!(cpu.get_flag(CpuFlags::Carry));
+        assert!(cpu.get_flag(CpuFlags::Zero));
+        assert!(!cpu.get_flag(CpuFlags::Negative));
+    }
+
+    #[test]
+    fn adc_follows_the_decimal_flag() {
+        let rom = [
+            0xf8,       // SED
+            0x69, 0x19, // ADC #$19
+            0xd8,       // CLD
+            0x69, 0x19, // ADC #$19
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        cpu.a = 0x28;
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(0x47, cpu.a); // 28 + 19
+        assert!(!cpu.get_flag(CpuFlags::Carry));
+
+        cpu.a = 0x28;
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(0x41, cpu.a);
+    }
+
+    #[test]
+    fn decimal_adc_carries_into_the_next_byte() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &[0x69, 0x01]).unwrap(); // ADC #$01
+        let mut cpu = Cpu::new(mem);
+        cpu.set_flag(CpuFlags::Decimal);
+        cpu.set_flag(CpuFlags::Carry);
+        cpu.a = 0x98;
+
+        cpu.step().unwrap();
+        assert_eq!(0x00, cpu.a);
+        assert!(cpu.get_flag(CpuFlags::Carry));
+    }
+
+    #[test]
+    fn adc_stays_binary_without_decimal_support() {
+        let rom = [
+            0xf8,       // SED
+            0x69, 0x19, // ADC #$19
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&warnings);
+        cpu.set_decimal_supported(false);
+        cpu.set_decimal_warning(Box::new(move |addr| sink.borrow_mut().push(addr)));
+
+        cpu.a = 0x28;
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert!(cpu.get_flag(CpuFlags::Decimal));
+        assert_eq!(0x41, cpu.a);
+        assert_eq!(vec![0x1000], *warnings.borrow());
+    }
+
+    #[test]
+    fn compare_sets_carry_when_the_register_is_not_smaller() {
+        let rom = [
+            0xc9, 0x20, // CMP #$20
+            0xc9, 0x10, // CMP #$10
+            0xe0, 0x05, // CPX #$05
+            0xc0, 0x90  // CPY #$90
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+        cpu.x = 0x05;
+        cpu.y = 0x10;
+
+        cpu.a = 0x10;
+        cpu.step().unwrap();
+        assert!(!cpu.get_flag(CpuFlags::Carry));
+        assert!(!cpu.get_flag(CpuFlags::Zero));
+        assert!(cpu.get_flag(CpuFlags::Negative)); // 0xf0
+
+        cpu.a = 0x20;
+        cpu.step().unwrap();
+        assert!(cpu.get_flag(CpuFlags::Carry));
+        assert!(!cpu.get_flag(CpuFlags::Zero));
+        assert!(!cpu.get_flag(CpuFlags::Negative));
+        assert_eq!(0x20, cpu.a);
+
+        cpu.step().unwrap();
+        assert!(cpu.get_flag(CpuFlags::Carry));
+        assert!(cpu.get_flag(CpuFlags::Zero));
+
+        // 0x10 - 0x90 = 0x80, so N is set even though the register is smaller
+        cpu.step().unwrap();
+        assert!(!cpu.get_flag(CpuFlags::Carry));
+        assert!(cpu.get_flag(CpuFlags::Negative));
+    }
+
+    #[test]
+    fn sbc_is_adc_of_the_complement() {
+        let pairs = [(0x50, 0xf0), (0x50, 0xb0), (0xd0, 0x70), (0x10, 0x20), (0x80, 0x01), (0x00, 0x00)];
+
+        for (a, b) in pairs {
+            let mut mem = Memory::new(64*1024).unwrap();
+            mem.load_rom(0x1000, &[0xe9, b, 0x69, 0xff - b]).unwrap(); // SBC #b, ADC #(255-b)
+            let mut sbc = Cpu::new(mem);
+            sbc.a = a;
+            sbc.set_flag(CpuFlags::Carry);
+            let mut adc = sbc.clone();
+            adc.pc = 0x1002;
+
+            sbc.step().unwrap();
+            adc.step().unwrap();
+            assert_eq!(a.wrapping_sub(b), sbc.a, "{:02x} - {:02x}", a, b);
+            assert_eq!(adc.a, sbc.a, "{:02x} - {:02x}", a, b);
+            assert_eq!(adc.sr, sbc.sr, "{:02x} - {:02x}", a, b);
+        }
+    }
+
+    #[test]
+    fn decimal_sbc_borrows() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &[0xe9, 0x19, 0xe9, 0x19]).unwrap(); // SBC #$19, twice
+        let mut cpu = Cpu::new(mem);
+        cpu.set_flag(CpuFlags::Decimal);
+        cpu.set_flag(CpuFlags::Carry);
+        cpu.a = 0x28;
+
+        cpu.step().unwrap();
+        assert_eq!(0x09, cpu.a);
+        assert!(cpu.get_flag(CpuFlags::Carry));
+
+        cpu.step().unwrap();
+        assert_eq!(0x90, cpu.a); // 09 - 19 borrows
+        assert!(!cpu.get_flag(CpuFlags::Carry));
+    }
+
+    #[test]
+    fn reset_clears_the_decimal_flag() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0xfffc, &[0x00, 0x10]).unwrap();
+        let mut cpu = Cpu::new(mem);
+        cpu.set_flag(CpuFlags::Decimal);
+
+        cpu.reset().unwrap();
+        assert!(!cpu.get_flag(CpuFlags::Decimal));
+    }
+}
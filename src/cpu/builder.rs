@@ -0,0 +1,91 @@
+use super::*;
+
+/// sets up the registers before the cpu exists, for tests living outside the crate
+///
+/// ```
+/// use ataritron::cpu::Cpu;
+/// use ataritron::memory::Memory;
+///
+/// let cpu = Cpu::builder(Memory::new(64*1024).unwrap())
+///     .pc(0x2000)
+///     .x(0x10)
+///     .build();
+///
+/// assert_eq!(cpu.pc(), 0x2000);
+/// assert_eq!(cpu.x(), 0x10);
+/// assert_eq!(cpu.y(), 0x00);
+/// ```
+pub struct CpuBuilder<B: Bus = Memory> {
+    memory: B,
+    state: CpuState,
+    variant: CpuVariant
+}
+
+impl<B: Bus> CpuBuilder<B> {
+    /// starts from the same registers as `Cpu::new`
+    pub fn new(mem : B) -> CpuBuilder<B> {
+        CpuBuilder {
+            memory: mem,
+            state: CpuState { pc: 0x1000, a: 0, x: 0, y: 0, sp: 0xff, sr: 0 },
+            variant: CpuVariant::Nmos
+        }
+    }
+
+    pub fn memory(mut self, mem : B) -> Self {
+        self.memory = mem;
+        self
+    }
+
+    pub fn pc(mut self, pc : u16) -> Self {
+        self.state.pc = pc;
+        self
+    }
+
+    pub fn a(mut self, a : u8) -> Self {
+        self.state.a = a;
+        self
+    }
+
+    pub fn x(mut self, x : u8) -> Self {
+        self.state.x = x;
+        self
+    }
+
+    pub fn y(mut self, y : u8) -> Self {
+        self.state.y = y;
+        self
+    }
+
+    pub fn sp(mut self, sp : u8) -> Self {
+        self.state.sp = sp;
+        self
+    }
+
+    pub fn sr(mut self, sr : u8) -> Self {
+        self.state.sr = sr;
+        self
+    }
+
+    pub fn variant(mut self, variant : CpuVariant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    pub fn build(self) -> Cpu<B> {
+        let mut cpu = Cpu::new(self.memory);
+        cpu.pc = self.state.pc;
+        cpu.a = self.state.a;
+        cpu.x = self.state.x;
+        cpu.y = self.state.y;
+        cpu.sp = self.state.sp;
+        cpu.sr = self.state.sr;
+        cpu.variant = self.variant;
+        cpu
+    }
+}
+
+impl<B: Bus> Cpu<B> {
+    pub fn builder(mem : B) -> CpuBuilder<B> {
+        CpuBuilder::new(mem)
+    }
+}
@@ -0,0 +1,614 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+/// what a single debugger step did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepReport {
+    /// where the instruction was
+    pub address : u16,
+    /// the instruction as assembly, like `1000: LDA #$42`
+    pub text : String,
+    pub cycles : u8,
+    /// the registers after the instruction ran
+    pub state : CpuState
+}
+
+/// why `run` returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// pc reached a breakpoint, which has not run yet
+    Breakpoint(u16),
+    /// the next instruction is a BRK at this address
+    Break(u16),
+    /// the instruction that just ran loaded `value` from a watched address
+    ReadWatchpoint { addr : u16, value : u8 },
+    /// the instruction that just ran stored `value` to a watched address
+    WriteWatchpoint { addr : u16, value : u8 },
+    /// the instruction at this address jumped or branched to itself, which is how test ROMs
+    /// report failures (and sometimes success)
+    TrapLoop(u16)
+}
+
+/// the last data accesses, oldest first, as (write, address, value)
+#[derive(Clone)]
+pub(super) struct AccessLog {
+    entries : VecDeque<(bool, u16, u8)>,
+    capacity : usize
+}
+
+impl AccessLog {
+    pub(super) fn record(&mut self, write : bool, addr : u16, value : u8) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((write, addr, value));
+    }
+}
+
+/// the instructions run since `enable_boundary_check`, to spot jumps into their operands
+#[derive(Clone, Default)]
+pub(super) struct BoundaryCheck {
+    /// where instructions started
+    starts : HashSet<u16>,
+    /// operand bytes of the instructions that ran
+    operands : HashSet<u16>
+}
+
+/// how `run_until_break` ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunResult {
+    /// the next instruction is a BRK
+    Break { cycles : u64 },
+    /// ran out of cycles without finishing
+    CycleCapReached { cycles : u64 },
+    /// the instruction at `pc` jumped or branched to itself
+    Trap { pc : u16, cycles : u64 }
+}
+
+impl RunResult {
+    /// the cycles run, however it ended
+    pub fn cycles(&self) -> u64 {
+        match *self {
+            RunResult::Break { cycles }
+                | RunResult::CycleCapReached { cycles }
+                | RunResult::Trap { cycles, .. } => cycles
+        }
+    }
+}
+
+impl<B: Bus> Cpu<B> {
+    pub fn add_breakpoint(&mut self, addr : u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr : u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    /// makes `run` stop after an instruction reads and/or writes `addr`.
+    /// only data accesses count, not instruction fetches or pointer reads
+    pub fn add_watchpoint(&mut self, addr : u16, on_read : bool, on_write : bool) {
+        if on_read {
+            self.read_watchpoints.insert(addr);
+        }
+        if on_write {
+            self.write_watchpoints.insert(addr);
+        }
+    }
+
+    pub fn remove_watchpoint(&mut self, addr : u16) {
+        self.read_watchpoints.remove(&addr);
+        self.write_watchpoints.remove(&addr);
+    }
+
+    /// starts remembering the last `capacity` data accesses, the same ones watchpoints see.
+    /// enabling it again starts an empty log
+    pub fn enable_access_log(&mut self, capacity : usize) {
+        self.access_log = Some(AccessLog { entries: VecDeque::with_capacity(capacity), capacity });
+    }
+
+    /// the logged accesses as (write, address, value), oldest first. empty when logging is off
+    pub fn access_log(&self) -> Vec<(bool, u16, u8)> {
+        self.access_log.as_ref()
+            .map(|log| log.entries.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// starts remembering where the instructions that run begin and end, beginning with the one
+    /// at `entry`. a jump, branch or return landing on the operand of one of them goes to the
+    /// hook set with `set_boundary_hook`. enabling it again forgets what was seen
+    pub fn enable_boundary_check(&mut self, entry : u16) {
+        let mut check = BoundaryCheck::default();
+        check.starts.insert(entry);
+        self.boundaries = Some(check);
+    }
+
+    /// installs a callback that receives the address of the instruction that jumped and the
+    /// address it landed on, when that's in the middle of an instruction that ran before
+    pub fn set_boundary_hook(&mut self, f : BoundaryHook) {
+        self.boundary_hook = Some(f);
+    }
+
+    /// called by `step` for every instruction. `next` is the address right after it
+    pub(super) fn check_boundaries(&mut self, start : u16, next : u16) {
+        let Some(check) = self.boundaries.as_mut() else {
+            return;
+        };
+
+        // landing is judged against what ran before this instruction, so look before recording it
+        let landed_inside = self.pc != next && check.operands.contains(&self.pc) && !check.starts.contains(&self.pc);
+
+        check.starts.insert(start);
+        let mut addr = start.wrapping_add(1);
+        while addr != next {
+            check.operands.insert(addr);
+            addr = addr.wrapping_add(1);
+        }
+
+        if landed_inside {
+            let target = self.pc;
+            check.starts.insert(target);
+            if let Some(hook) = self.boundary_hook.as_mut() {
+                hook(start, target);
+            }
+        }
+    }
+
+    /// whether the instruction that just ran from `pc` went back to itself. `ready` is RDY as
+    /// it was before the step: with RDY low nothing ran, so that's not a trap, even when the
+    /// step's cycle raised RDY again
+    fn trapped(&self, pc : u16, ready : bool) -> bool {
+        ready && self.pc == pc
+    }
+
+    /// steps until pc reaches a breakpoint or a BRK, an instruction touches a watchpoint
+    /// or jumps to itself. a breakpoint at the starting pc is ignored, so calling `run` again continues
+    pub fn run(&mut self) -> Result<StopReason, CpuError> {
+        let mut first = true;
+
+        loop {
+            if !first && self.breakpoints.contains(&self.pc) {
+                return Ok(StopReason::Breakpoint(self.pc));
+            }
+            if self.memory.peek(self.pc)? == 0x00 {
+                return Ok(StopReason::Break(self.pc));
+            }
+
+            let pc = self.pc;
+            let ready = self.is_ready();
+            self.watch_hit = None;
+            self.step()?;
+            if let Some(hit) = self.watch_hit.take() {
+                return Ok(hit);
+            }
+            if self.trapped(pc, ready) {
+                return Ok(StopReason::TrapLoop(pc));
+            }
+            first = false;
+        }
+    }
+
+    /// steps until the next instruction is a BRK, an instruction jumps to itself or at least
+    /// `max_cycles` cycles ran, and says which one happened
+    pub fn run_until_break(&mut self, max_cycles : u64) -> Result<RunResult, CpuError> {
+        let mut cycles = 0;
+
+        loop {
+            if self.memory.peek(self.pc)? == 0x00 {
+                return Ok(RunResult::Break { cycles });
+            }
+            if cycles >= max_cycles {
+                return Ok(RunResult::CycleCapReached { cycles });
+            }
+
+            let pc = self.pc;
+            let ready = self.is_ready();
+            cycles += self.step()? as u64;
+            if self.trapped(pc, ready) {
+                return Ok(RunResult::Trap { pc, cycles });
+            }
+        }
+    }
+
+    /// like `run_until_break`, but capped by instructions rather than cycles, and running out
+    /// is an error. returns the instructions run
+    pub fn run_max_instructions(&mut self, n : u64) -> Result<u64, CpuError> {
+        for count in 0..n {
+            if self.memory.peek(self.pc)? == 0x00 {
+                return Ok(count);
+            }
+
+            let pc = self.pc;
+            let ready = self.is_ready();
+            self.step()?;
+            if self.trapped(pc, ready) {
+                return Ok(count + 1);
+            }
+        }
+
+        Err(CpuError::InstructionLimitReached(n))
+    }
+
+    /// runs whole instructions until at least `budget` cycles went by, like a frame's worth.
+    /// returns the cycles run, which can go a few over the budget
+    pub fn run_cycles(&mut self, budget : u64) -> Result<u64, CpuError> {
+        let mut cycles = 0;
+        while cycles < budget {
+            cycles += self.step()? as u64;
+        }
+
+        Ok(cycles)
+    }
+
+    /// runs one instruction and describes it
+    pub fn debug_step(&mut self) -> Result<StepReport, CpuError> {
+        let address = self.pc;
+        let text = self.disassemble(address, 1)
+            .pop()
+            .map(|(_, text)| text)
+            .unwrap_or_default();
+        let cycles = self.step()?;
+
+        Ok(StepReport {
+            address,
+            text,
+            cycles,
+            state: self.state()
+        })
+    }
+
+    /// runs one instruction and returns the registers after each cycle it took, for a timeline view.
+    /// TODO: instructions still run in one go, so every entry is the state after the last cycle.
+    /// splitting them into their bus accesses would give the real intermediate states
+    pub fn step_with_timeline(&mut self) -> Result<Vec<CpuState>, CpuError> {
+        let cycles = self.step()?;
+        Ok(vec![self.state(); cycles as usize])
+    }
+
+    /// the address the instruction at `addr` would touch with the current registers,
+    /// like `$0025` for `LDA $20,X` with X at 5. None when it has no address, like `LDA #$00`
+    pub fn effective_address_at(&self, addr : u16) -> Result<Option<u16>, CpuError> {
+        let (instruction, size) = self.decode_at(addr)?;
+
+        match instruction.addressing {
+            Addressing::Immediate(_) | Addressing::Implied => Ok(None),
+            // branches are relative to the next instruction, not to wherever pc is now
+            Addressing::RelativeAddress(offset) => Ok(Some(relative_target(addr.wrapping_add(size), offset))),
+            addressing => self.effective_address(addressing, true).map(Some)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn run_reports_a_jump_to_itself() {
+        let rom = [
+            0xea,             // NOP
+            0x4c, 0x01, 0x10  // JMP $1001
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        assert_eq!(StopReason::TrapLoop(0x1001), cpu.run().unwrap());
+        assert_eq!(0x1001, cpu.pc);
+    }
+
+    #[test]
+    fn run_until_break_reports_a_brk() {
+        let rom = [
+            0xea,             // NOP
+            0xea,             // NOP
+            0x00
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        assert_eq!(RunResult::CycleCapReached { cycles: 2 }, cpu.run_until_break(2).unwrap());
+        assert_eq!(0x1001, cpu.pc);
+        assert_eq!(RunResult::Break { cycles: 2 }, cpu.run_until_break(100).unwrap());
+        assert_eq!(0x1002, cpu.pc);
+    }
+
+    #[test]
+    fn run_until_break_reports_the_cap() {
+        let rom = [
+            0xea,             // NOP
+            0x4c, 0x00, 0x10  // JMP $1000
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        let result = cpu.run_until_break(100).unwrap();
+        assert_eq!(RunResult::CycleCapReached { cycles: 100 }, result);
+        assert_eq!(100, result.cycles());
+    }
+
+    #[test]
+    fn run_until_break_reports_a_trap() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x2000, &[0x4c, 0x00, 0x20]).unwrap(); // JMP $2000
+        let mut cpu = Cpu::new(mem);
+        cpu.pc = 0x2000;
+
+        assert_eq!(RunResult::Trap { pc: 0x2000, cycles: 3 }, cpu.run_until_break(100).unwrap());
+        assert_eq!(0x2000, cpu.pc);
+    }
+
+    #[test]
+    fn run_cycles_runs_whole_instructions() {
+        let rom = [
+            0xad, 0x00, 0x20, // LDA $2000, 4 cycles
+            0x4c, 0x00, 0x10  // JMP $1000, 3 cycles
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        // 14 loops are 98 cycles, then one more LDA
+        let cycles = cpu.run_cycles(100).unwrap();
+        assert_eq!(102, cycles);
+        assert!((100..107).contains(&cycles));
+        assert_eq!(0x1003, cpu.pc);
+        assert_eq!(cycles, cpu.cycles());
+
+        assert_eq!(0, cpu.run_cycles(0).unwrap());
+    }
+
+    #[test]
+    fn run_max_instructions_stops_at_the_limit() {
+        let rom = [
+            0xea,             // NOP
+            0x4c, 0x00, 0x10  // JMP $1000
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        mem.load_rom(0x2000, &[0xea, 0xea, 0x00]).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        assert!(matches!(cpu.run_max_instructions(1000), Err(CpuError::InstructionLimitReached(1000))));
+        assert_eq!(0x1000, cpu.pc);
+
+        cpu.pc = 0x2000;
+        assert_eq!(2, cpu.run_max_instructions(1000).unwrap());
+        assert_eq!(0x2002, cpu.pc);
+    }
+
+    #[test]
+    fn waiting_for_wsync_is_not_a_trap() {
+        let rom = [
+            0x85, 0x02, // STA WSYNC
+            0xa9, 0x01, // LDA #$01
+            0x00
+        ];
+        let wsync_cpu = || {
+            let mut mem = Memory::new_atari();
+            mem.load_rom(0x1000, &rom).unwrap();
+            Cpu::new(mem)
+        };
+
+        let mut cpu = wsync_cpu();
+        assert_eq!(StopReason::Break(0x1004), cpu.run().unwrap());
+        assert_eq!(0x01, cpu.a);
+
+        let mut cpu = wsync_cpu();
+        assert_eq!(RunResult::Break { cycles: 78 }, cpu.run_until_break(1000).unwrap());
+
+        let mut cpu = wsync_cpu();
+        // the halted cycles count as steps too
+        assert!(cpu.run_max_instructions(1000).unwrap() > 2);
+        assert_eq!(0x1004, cpu.pc);
+    }
+
+    #[test]
+    fn reports_a_step() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &[0xa9, 0x42]).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        let report = cpu.debug_step().unwrap();
+        assert_eq!(0x1000, report.address);
+        assert_eq!("1000: LDA #$42", report.text);
+        assert_eq!(2, report.cycles);
+        assert_eq!(0x42, report.state.a);
+        assert_eq!(0x1002, report.state.pc);
+    }
+
+    #[test]
+    fn timeline_has_a_state_per_cycle() {
+        let rom = [
+            0xa9, 0x42,       // LDA #$42
+            0xbd, 0xff, 0x10, // LDA $10ff,X
+            0x20, 0x00, 0x20  // JSR $2000
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        let timeline = cpu.step_with_timeline().unwrap();
+        assert_eq!(2, timeline.len());
+        assert_eq!(0x42, timeline[1].a);
+
+        // the page cross adds a cycle
+        cpu.x = 0x01;
+        assert_eq!(5, cpu.step_with_timeline().unwrap().len());
+
+        let timeline = cpu.step_with_timeline().unwrap();
+        assert_eq!(6, timeline.len());
+        assert_eq!(cpu.state(), timeline[5]);
+    }
+
+    #[test]
+    fn run_stops_at_breakpoints_in_a_loop() {
+        let rom = vec![
+            0xea,
+            0xea,
+            0xea,             // breakpoint
+            0x4c, 0x00, 0x10  // JMP $1000
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+        cpu.add_breakpoint(0x1002);
+
+        assert_eq!(StopReason::Breakpoint(0x1002), cpu.run().unwrap());
+        assert_eq!(0x1002, cpu.pc);
+        assert_eq!(4, cpu.cycles());
+
+        // a full trip around the loop: NOP, JMP, NOP, NOP
+        assert_eq!(StopReason::Breakpoint(0x1002), cpu.run().unwrap());
+        assert_eq!(0x1002, cpu.pc);
+        assert_eq!(13, cpu.cycles());
+    }
+
+    #[test]
+    fn run_stops_on_watched_writes() {
+        let rom = vec![
+            0xa9, 0x7e,
+            0xa6, 0x10,  // LDX $10 only reads
+            0x85, 0x10,  // STA $10
+            0xea,
+            0x00
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+        cpu.add_watchpoint(0x0010, false, true);
+
+        assert_eq!(StopReason::WriteWatchpoint { addr: 0x0010, value: 0x7e }, cpu.run().unwrap());
+        assert_eq!(0x1006, cpu.pc);
+
+        cpu.remove_watchpoint(0x0010);
+        assert_eq!(StopReason::Break(0x1007), cpu.run().unwrap());
+    }
+
+    #[test]
+    fn run_stops_on_watched_reads() {
+        let rom = vec![
+            0xa9, 0x7e,
+            0xa5, 0x10,
+            0x00
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        mem.store(0x0010, 0x33).unwrap();
+        let mut cpu = Cpu::new(mem);
+        cpu.add_watchpoint(0x0010, true, false);
+
+        assert_eq!(StopReason::ReadWatchpoint { addr: 0x0010, value: 0x33 }, cpu.run().unwrap());
+        assert_eq!(0x1004, cpu.pc);
+    }
+
+    #[test]
+    fn run_stops_at_brk() {
+        let rom = vec![
+            0xea,
+            0xa9, 0x01,
+            0x00
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+        cpu.add_breakpoint(0x1001);
+        cpu.remove_breakpoint(0x1001);
+
+        assert_eq!(StopReason::Break(0x1003), cpu.run().unwrap());
+        assert_eq!(0x01, cpu.a);
+        assert_eq!(4, cpu.cycles());
+    }
+
+    #[test]
+    fn resolves_effective_addresses() {
+        let rom = vec![
+            0xb5, 0x20, // LDA $20,X
+            0xa9, 0x00, // LDA #$00
+            0xd0, 0xfa  // BNE $1000
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+        cpu.x = 0x05;
+
+        assert_eq!(Some(0x0025), cpu.effective_address_at(0x1000).unwrap());
+        assert_eq!(None, cpu.effective_address_at(0x1002).unwrap());
+        assert_eq!(Some(0x1000), cpu.effective_address_at(0x1004).unwrap());
+        assert_eq!(0x1000, cpu.pc);
+    }
+
+    #[test]
+    fn logs_the_last_accesses() {
+        let rom = vec![
+            0xa5, 0x10, // LDA $10
+            0x85, 0x11, // STA $11
+            0xe6, 0x12, // INC $12
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        mem.load_rom(0x0010, &[0x33, 0x00, 0x7f]).unwrap();
+        let mut cpu = Cpu::new(mem);
+        assert!(cpu.access_log().is_empty());
+
+        cpu.enable_access_log(4);
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(vec![(false, 0x0010, 0x33), (true, 0x0011, 0x33)], cpu.access_log());
+
+        // INC reads, writes the old value back and then the new one, pushing out the LDA
+        cpu.step().unwrap();
+        assert_eq!(vec![
+            (true, 0x0011, 0x33),
+            (false, 0x0012, 0x7f),
+            (true, 0x0012, 0x7f),
+            (true, 0x0012, 0x80)
+        ], cpu.access_log());
+    }
+
+    #[test]
+    fn reports_jumps_into_operands() {
+        let rom = vec![
+            0xa9, 0x42,       // LDA #$42
+            0x4c, 0x01, 0x10, // JMP $1001, the LDA's operand
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+        let jumps = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&jumps);
+        cpu.enable_boundary_check(0x1000);
+        cpu.set_boundary_hook(Box::new(move |from, to| sink.borrow_mut().push((from, to))));
+
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(vec![(0x1002, 0x1001)], *jumps.borrow());
+        assert_eq!(0x1001, cpu.pc);
+    }
+
+    #[test]
+    fn jumps_to_instruction_starts_are_fine() {
+        let rom = vec![
+            0xa9, 0x42,       // LDA #$42
+            0x4c, 0x00, 0x10, // JMP $1000
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+        let jumps = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&jumps);
+        cpu.enable_boundary_check(0x1000);
+        cpu.set_boundary_hook(Box::new(move |from, to| sink.borrow_mut().push((from, to))));
+
+        for _ in 0..6 {
+            cpu.step().unwrap();
+        }
+        assert!(jumps.borrow().is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use std::ops::RangeInclusive;
+
+use super::*;
+
+/// renders the operand in the usual 6502 assembler syntax.
+/// `next_addr` is the address right after the instruction, used to resolve branch targets
+fn format_operand(addressing : &Addressing, index : char, next_addr : u16) -> String {
+    match addressing {
+        Addressing::Implied => String::new(),
+        Addressing::Immediate(value) => format!("#${:02X}", value),
+        Addressing::Absolute(addr) => format!("${:04X}", addr),
+        Addressing::Zeropage(addr) => format!("${:02X}", addr),
+        Addressing::IndexedAbsolute(base, _) => format!("${:04X},{}", base, index),
+        Addressing::IndexedZeropage(base, _) => format!("${:02X},{}", base, index),
+        Addressing::Indirect(addr) => format!("(${:04X})", addr),
+        Addressing::PreindexedIndirect(base, _) => format!("(${:02X},X)", base),
+        Addressing::PostindexedIndirect(base, _) => format!("(${:02X}),Y", base),
+        Addressing::RelativeAddress(offset) => format!("${:04X}", relative_target(next_addr, *offset)),
+    }
+}
+
+impl<B: Bus> Cpu<B> {
+    /// decodes `count` instructions starting at `start` into assembly text, like `1000: LDA #$BA`.
+    /// illegal opcodes show up as `.byte $02`. stops early if the memory ends before `count`
+    /// instructions were decoded. does not move the program counter
+    pub fn disassemble(&self, start : u16, count : usize) -> Vec<(u16, String)> {
+        self.disassemble_with_data(start, count, &[])
+    }
+
+    /// like `disassemble`, but the bytes in `data` are tables and graphics rather than code,
+    /// so each of them becomes a `.byte $xx` line instead of being decoded
+    pub fn disassemble_with_data(&self, start : u16, count : usize, data : &[RangeInclusive<u16>]) -> Vec<(u16, String)> {
+        let mut lines = Vec::with_capacity(count);
+        let mut addr = start;
+
+        for _ in 0..count {
+            if data.iter().any(|region| region.contains(&addr)) {
+                let Ok(byte) = self.memory.peek(addr) else {
+                    break;
+                };
+                lines.push((addr, format!("{:04X}: .byte ${:02X}", addr, byte)));
+                addr = addr.wrapping_add(1);
+                continue;
+            }
+
+            let (instruction, size) = match self.decode_at(addr) {
+                Ok(decoded) => decoded,
+                Err(CpuError::IllegalOpcode { opcode, .. }) => {
+                    lines.push((addr, format!("{:04X}: .byte ${:02X}", addr, opcode)));
+                    addr = addr.wrapping_add(1);
+                    continue;
+                },
+                Err(_) => break
+            };
+            let opcode = self.memory.peek(addr).unwrap(); // decode_at already read it
+            let next_addr = addr.wrapping_add(size);
+
+            let operand = format_operand(&instruction.addressing, index_register(opcode), next_addr);
+            let text = if operand.is_empty() {
+                format!("{:04X}: {}", addr, instruction.operation)
+            } else {
+                format!("{:04X}: {} {}", addr, instruction.operation, operand)
+            };
+            lines.push((addr, text));
+
+            addr = next_addr;
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassembles_lda_instructions() {
+        let rom = vec![
+            0xa9, 0xba,
+            0xa5, 0x11,
+            0xb5, 0x23,
+            0xad, 0x50, 0x23,
+            0xbd, 0x04, 0x23,
+            0xb9, 0x77, 0x42,
+            0xa1, 0x80,
+            0xb1, 0x33
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let cpu = Cpu::new(mem);
+
+        let lines = cpu.disassemble(0x1000, 8);
+        assert_eq!(lines, vec![
+            (0x1000, String::from("1000: LDA #$BA")),
+            (0x1002, String::from("1002: LDA $11")),
+            (0x1004, String::from("1004: LDA $23,X")),
+            (0x1006, String::from("1006: LDA $2350")),
+            (0x1009, String::from("1009: LDA $2304,X")),
+            (0x100c, String::from("100C: LDA $4277,Y")),
+            (0x100f, String::from("100F: LDA ($80,X)")),
+            (0x1011, String::from("1011: LDA ($33),Y")),
+        ]);
+        assert_eq!(cpu.pc, 0x1000);
+    }
+
+    #[test]
+    fn disassembling_over_the_hotspots_keeps_the_bank() {
+        let mut image = vec![0xaa; 0x1000];
+        image.extend(vec![0xbb; 0x1000]);
+        image[0x1ff0..0x1ff6].copy_from_slice(&[
+            0xad, 0xf8, 0x1f, // LDA $1FF8
+            0xbd, 0xf6, 0x1f  // LDA $1FF6,X
+        ]);
+        let cpu = Cpu::new(Memory::with_cartridge(&image).unwrap());
+
+        let lines = cpu.disassemble(0x1ff0, 8);
+        assert_eq!("1FF0: LDA $1FF8", lines[0].1);
+        assert_eq!("1FF3: LDA $1FF6,X", lines[1].1);
+        assert_eq!("1FF8: .byte $BB", lines[4].1);
+        cpu.decode_at(0x1ff8).ok();
+        assert_eq!(Some(0x1ff8), cpu.effective_address_at(0x1ff0).unwrap());
+        assert_eq!(Some(0x1ff6), cpu.effective_address_at(0x1ff3).unwrap());
+
+        assert_eq!(0xbb, cpu.memory.load(0x1000).unwrap());
+    }
+
+    #[test]
+    fn disassembles_y_indexed_and_indirect_operands() {
+        let rom = vec![
+            0xb6, 0x10,       // LDX $10,Y
+            0x96, 0x10,       // STX $10,Y
+            0xbe, 0x34, 0x12, // LDX $1234,Y
+            0xb9, 0x34, 0x12, // LDA $1234,Y
+            0xbc, 0x34, 0x12, // LDY $1234,X
+            0x6c, 0xfc, 0xff  // JMP ($FFFC)
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let cpu = Cpu::new(mem);
+
+        let lines : Vec<String> = cpu.disassemble(0x1000, 6).into_iter().map(|(_, text)| text).collect();
+        assert_eq!(lines, vec![
+            "1000: LDX $10,Y",
+            "1002: STX $10,Y",
+            "1004: LDX $1234,Y",
+            "1007: LDA $1234,Y",
+            "100A: LDY $1234,X",
+            "100D: JMP ($FFFC)"
+        ]);
+    }
+
+    #[test]
+    fn disassembles_branch_targets() {
+        let rom = vec![
+            0xd0, 0xfe, // BNE to itself
+            0x10, 0x02,
+            0x60
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let cpu = Cpu::new(mem);
+
+        let lines = cpu.disassemble(0x1000, 3);
+        assert_eq!("1000: BNE $1000", lines[0].1);
+        assert_eq!("1002: BPL $1006", lines[1].1);
+        assert_eq!("1004: RTS", lines[2].1);
+    }
+
+    #[test]
+    fn shows_data_regions_as_bytes() {
+        let rom = vec![
+            0xa9, 0x01, // LDA #$01
+            0x20, 0xff, 0x00, // data that happens to look like a JSR
+            0x60
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x0000, &rom).unwrap();
+        let cpu = Cpu::new(mem);
+
+        let lines = cpu.disassemble_with_data(0x0000, 5, &[0x0002..=0x0004]);
+        let text : Vec<&str> = lines.iter().map(|(_, text)| text.as_str()).collect();
+        assert_eq!(vec![
+            "0000: LDA #$01",
+            "0002: .byte $20",
+            "0003: .byte $FF",
+            "0004: .byte $00",
+            "0005: RTS"
+        ], text);
+    }
+
+    #[test]
+    fn shows_illegal_opcodes_as_bytes() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &[0x02, 0xea]).unwrap();
+        let cpu = Cpu::new(mem);
+
+        let lines = cpu.disassemble(0x1000, 2);
+        assert_eq!("1000: .byte $02", lines[0].1);
+        assert_eq!("1001: NOP", lines[1].1);
+    }
+}
@@ -6,7 +6,18 @@ use crate::memory::OutOfRangeError;
 #[derive(Debug)]
 pub enum CpuError {
 	InvalidAddressModeDerefenced,
-	MemoryBoundsError(OutOfRangeError)
+	MemoryBoundsError(OutOfRangeError),
+	IncompatibleSaveState(&'static str),
+	/// the operands of the instruction at `at` run past the end of memory
+	IncompleteInstruction { opcode : u8, at : u16 },
+	/// one of the undocumented opcodes, which aren't emulated
+	IllegalOpcode { opcode : u8, at : u16 },
+	/// `run_max_instructions` ran out of instructions before reaching a BRK or a trap
+	InstructionLimitReached(u64),
+	/// a push with the stack pointer at 0x00, with the strict stack on
+	StackOverflow,
+	/// a pull with the stack pointer at 0xff, with the strict stack on
+	StackUnderflow
 }
 
 impl Error for CpuError {}
@@ -15,7 +26,19 @@ impl fmt::Display for CpuError {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		match self {
 			Self::InvalidAddressModeDerefenced => write!(f, "Invalid address mode dereferenced"),
-			Self::MemoryBoundsError(e) => write!(f, "{}", e.to_string())
+			Self::MemoryBoundsError(e) => write!(f, "{}", e),
+			Self::IncompatibleSaveState(reason) => write!(f, "Incompatible save state: {}", reason),
+			Self::IncompleteInstruction { opcode, at } => write!(f, "Instruction {:#04x} at {:#06x} runs past the end of memory", opcode, at),
+			Self::IllegalOpcode { opcode, at } => write!(f, "Illegal opcode {:#04x} at {:#06x}", opcode, at),
+			Self::InstructionLimitReached(limit) => write!(f, "Reached the limit of {} instructions", limit),
+			Self::StackOverflow => write!(f, "Stack overflow"),
+			Self::StackUnderflow => write!(f, "Stack underflow")
 		}
 	}
 }
+
+impl From<OutOfRangeError> for CpuError {
+	fn from(e: OutOfRangeError) -> Self {
+		Self::MemoryBoundsError(e)
+	}
+}
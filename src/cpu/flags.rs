@@ -1,28 +1,105 @@
 use super::*;
 
-enum CpuFlags {
+pub enum CpuFlags {
     Carry = 0,
     Zero = 1,
     InterruptDisable = 2,
-    _Decimal = 3,
+    Decimal = 3,
     BreakFlag = 4,
     _Unused = 5,
     Overflow = 6,
     Negative = 7
 }
 
-impl Cpu {
-    fn get_flag(&self, flag : CpuFlags) -> bool {
-        self.sr & (0x1 << (flag as u8)) != 0
+/// the status register with a field per flag
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Flags {
+    pub carry : bool,
+    pub zero : bool,
+    pub interrupt_disable : bool,
+    pub decimal : bool,
+    pub break_flag : bool,
+    pub overflow : bool,
+    pub negative : bool
+}
+
+impl<B: Bus> Cpu<B> {
+    pub fn flag(&self, flag : CpuFlags) -> bool {
+        self.get_flag(flag)
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags {
+            carry: self.get_flag(CpuFlags::Carry),
+            zero: self.get_flag(CpuFlags::Zero),
+            interrupt_disable: self.get_flag(CpuFlags::InterruptDisable),
+            decimal: self.get_flag(CpuFlags::Decimal),
+            break_flag: self.get_flag(CpuFlags::BreakFlag),
+            overflow: self.get_flag(CpuFlags::Overflow),
+            negative: self.get_flag(CpuFlags::Negative)
+        }
+    }
+
+    /// replaces the whole status register. the unused bit stays set
+    pub fn set_flags(&mut self, flags : Flags) {
+        self.sr = 0x1 << (CpuFlags::_Unused as u8);
+        self.set_flag_to(CpuFlags::Carry, flags.carry);
+        self.set_flag_to(CpuFlags::Zero, flags.zero);
+        self.set_flag_to(CpuFlags::InterruptDisable, flags.interrupt_disable);
+        self.set_flag_to(CpuFlags::Decimal, flags.decimal);
+        self.set_flag_to(CpuFlags::BreakFlag, flags.break_flag);
+        self.set_flag_to(CpuFlags::Overflow, flags.overflow);
+        self.set_flag_to(CpuFlags::Negative, flags.negative);
+    }
+
+    /// sets a single flag, e.g. the carry before running an ADC or a ROL in a test
+    pub fn set_flag_for_test(&mut self, flag : CpuFlags) {
+        self.set_flag(flag);
+    }
+
+    pub fn clear_flag_for_test(&mut self, flag : CpuFlags) {
+        self.clear_flag(flag);
     }
 
-    fn set_flag(&mut self, flag : CpuFlags) {
+    pub(super) fn get_flag(&self, flag : CpuFlags) -> bool {
+        self.status() & (0x1 << (flag as u8)) != 0
+    }
+
+    /// the status register as programs see it. bit 5 isn't wired to anything and always reads as set
+    pub(super) fn status(&self) -> u8 {
+        self.sr | 0x1 << (CpuFlags::_Unused as u8)
+    }
+
+    pub(super) fn set_flag(&mut self, flag : CpuFlags) {
         self.sr |= 0x1 << (flag as u8);
     }
 
-    fn clear_flag(&mut self, flag : CpuFlags) {
+    pub(super) fn clear_flag(&mut self, flag : CpuFlags) {
         self.sr &= !(0x1 << (flag as u8));
     }
+
+    pub(super) fn set_flag_to(&mut self, flag : CpuFlags, value : bool) {
+        if value {
+            self.set_flag(flag);
+        } else {
+            self.clear_flag(flag);
+        }
+    }
+
+    /// sets the zero and negative flags according to a result
+    pub(super) fn set_nz(&mut self, value : u8) {
+        if value == 0 {
+            self.set_flag(CpuFlags::Zero);
+        } else {
+            self.clear_flag(CpuFlags::Zero);
+        }
+
+        if value & 0x80 != 0 {
+            self.set_flag(CpuFlags::Negative);
+        } else {
+            self.clear_flag(CpuFlags::Negative);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -30,6 +107,25 @@ mod tests {
     use super::*;
 
     #[test]
+    fn decodes_flags_into_a_struct() {
+        let mem = Memory::new(64*1024).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        cpu.sr = 0b11000011;
+        assert_eq!(Flags {
+            carry: true,
+            zero: true,
+            overflow: true,
+            negative: true,
+            ..Default::default()
+        }, cpu.flags());
+
+        cpu.set_flags(Flags { decimal: true, interrupt_disable: true, ..Default::default() });
+        assert_eq!(0b0010_1100, cpu.sr);
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn can_read_flags() {
         let mem = Memory::new(64*1024).unwrap();
         let mut cpu = Cpu::new(mem);
@@ -81,4 +177,36 @@ mod tests {
 
         assert_eq!(0b00101000, cpu.sr);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn set_nz_follows_the_result() {
+        let mem = Memory::new(64*1024).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        // value, zero, negative
+        for (value, zero, negative) in [(0x00, true, false), (0x7f, false, false), (0x80, false, true)] {
+            cpu.set_nz(value);
+            assert_eq!(zero, cpu.get_flag(CpuFlags::Zero), "{:02x}", value);
+            assert_eq!(negative, cpu.get_flag(CpuFlags::Negative), "{:02x}", value);
+        }
+    }
+
+    #[test]
+    fn flags_set_for_tests_reach_the_instructions() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &[0x2a, 0x2a]).unwrap(); // ROL A, twice
+        let mut cpu = Cpu::new(mem);
+        cpu.a = 0x40;
+
+        cpu.set_flag_for_test(CpuFlags::Carry);
+        cpu.step().unwrap();
+        assert_eq!(0x81, cpu.a);
+        assert!(!cpu.flag(CpuFlags::Carry));
+
+        cpu.set_flag_for_test(CpuFlags::Carry);
+        cpu.clear_flag_for_test(CpuFlags::Carry);
+        cpu.step().unwrap();
+        assert_eq!(0x02, cpu.a);
+        assert!(cpu.flag(CpuFlags::Carry));
+    }
+}
@@ -0,0 +1,39 @@
+use super::*;
+use crate::riot::{ConsoleSwitches, Joystick};
+
+impl Cpu<Memory> {
+    /// port 0 is the left joystick, port 1 the right one.
+    /// does nothing unless the memory decodes like the 2600
+    pub fn set_joystick(&mut self, port : u8, joystick : Joystick) {
+        if let Some(riot) = self.memory.riot_mut() {
+            riot.set_joystick(port, joystick);
+        }
+    }
+
+    pub fn set_console_switches(&mut self, switches : ConsoleSwitches) {
+        if let Some(riot) = self.memory.riot_mut() {
+            riot.set_console_switches(switches);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joystick_up_clears_its_swcha_bit() {
+        let rom = vec![
+            0xad, 0x80, 0x02 // LDA SWCHA
+        ];
+        let mut mem = Memory::new_atari();
+        mem.load_rom(0x1000, &rom).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        cpu.set_joystick(0, Joystick { up: true, ..Default::default() });
+        cpu.step().unwrap();
+
+        assert_eq!(0, cpu.a & 0x10);
+        assert_eq!(0xef, cpu.a);
+    }
+}
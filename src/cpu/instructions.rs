@@ -1,4 +1,10 @@
-#[derive(Debug)]
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Addressing {
     Implied,
     Immediate(u8),
@@ -12,25 +18,58 @@ pub enum Addressing {
     PostindexedIndirect(u8, u8),
     RelativeAddress(u8), // used in conditional branching instructions
 }
-impl PartialEq for Addressing {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Addressing::Implied, Addressing::Implied) => true,
-            (Addressing::Immediate(a), Addressing::Immediate(b)) => a == b,
-            (Addressing::Absolute(a), Addressing::Absolute(b)) => a == b,
-            (Addressing::Zeropage(a), Addressing::Zeropage(b)) => a == b,
-            (Addressing::IndexedAbsolute(a1, a2), Addressing::IndexedAbsolute(b1, b2)) => a1 == b1 && a2 == b2,
-            (Addressing::IndexedZeropage(a1, a2), Addressing::IndexedZeropage(b1, b2)) => a1 == b1 && a2 == b2,
-            (Addressing::Indirect(a), Addressing::Indirect(b)) => a == b,
-            (Addressing::PreindexedIndirect(a1, a2), Addressing::PreindexedIndirect(b1, b2)) => a1 == b1 && a2 == b2,
-            (Addressing::PostindexedIndirect(a1, a2), Addressing::PostindexedIndirect(b1, b2)) => a1 == b1 && a2 == b2,
-            (Addressing::RelativeAddress(a), Addressing::RelativeAddress(b)) => a == b,
-            _ => false
+
+/// which addressing mode an `Addressing` uses, without its operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressingMode {
+    Implied,
+    Immediate,
+    Absolute,
+    Zeropage,
+    IndexedAbsolute,
+    IndexedZeropage,
+    Indirect,
+    PreindexedIndirect,
+    PostindexedIndirect,
+    RelativeAddress
+}
+
+impl AddressingMode {
+    /// opcode plus operand bytes
+    pub fn instruction_size(&self) -> u16 {
+        match self {
+            Self::Implied => 1,
+            Self::Immediate
+                | Self::Zeropage
+                | Self::IndexedZeropage
+                | Self::PreindexedIndirect
+                | Self::PostindexedIndirect
+                | Self::RelativeAddress
+            => 2,
+            Self::Absolute | Self::IndexedAbsolute | Self::Indirect => 3
         }
     }
 }
 
-#[derive(Debug)]
+impl Addressing {
+    pub fn mode(&self) -> AddressingMode {
+        match self {
+            Self::Implied => AddressingMode::Implied,
+            Self::Immediate(_) => AddressingMode::Immediate,
+            Self::Absolute(_) => AddressingMode::Absolute,
+            Self::Zeropage(_) => AddressingMode::Zeropage,
+            Self::IndexedAbsolute(_, _) => AddressingMode::IndexedAbsolute,
+            Self::IndexedZeropage(_, _) => AddressingMode::IndexedZeropage,
+            Self::Indirect(_) => AddressingMode::Indirect,
+            Self::PreindexedIndirect(_, _) => AddressingMode::PreindexedIndirect,
+            Self::PostindexedIndirect(_, _) => AddressingMode::PostindexedIndirect,
+            Self::RelativeAddress(_) => AddressingMode::RelativeAddress
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Operations {
     LoadAccumulator,
     LoadX,
@@ -89,9 +128,75 @@ pub enum Operations {
     BitTest,
     NoOperation
 }
-impl PartialEq for Operations {
-    fn eq(&self, other: &Self) -> bool {
-        std::mem::discriminant(self) == std::mem::discriminant(other)
+
+impl Operations {
+    /// the three-letter mnemonic, like `LDA`
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::LoadAccumulator => "LDA",
+            Self::LoadX => "LDX",
+            Self::LoadY => "LDY",
+            Self::StoreAccumulator => "STA",
+            Self::StoreX => "STX",
+            Self::StoreY => "STY",
+            Self::TransferAccumulatorToX => "TAX",
+            Self::TransferAccumulatorToY => "TAY",
+            Self::TransferStackPointerToX => "TSX",
+            Self::TransferXToStackPointer => "TXS",
+            Self::TransferXToAccumulator => "TXA",
+            Self::TransferYToAccumulator => "TYA",
+            Self::PushAccumulator => "PHA",
+            Self::PushStatusRegister => "PHP",
+            Self::PullAccumulator => "PLA",
+            Self::PullStatusRegister => "PLP",
+            Self::DecrementMemory => "DEC",
+            Self::DecrementX => "DEX",
+            Self::DecrementY => "DEY",
+            Self::IncrementMemory => "INC",
+            Self::IncrementX => "INX",
+            Self::IncrementY => "INY",
+            Self::AddWithCarry => "ADC",
+            Self::SubtractWithCarry => "SBC",
+            Self::AndWithAccumulator => "AND",
+            Self::ExclusiveOrWithAccumulator => "EOR",
+            Self::InclusiveOrWithAccumulator => "ORA",
+            Self::ArithmeticShiftLeft => "ASL",
+            Self::LogicalShiftRight => "LSR",
+            Self::RotateLeft => "ROL",
+            Self::RotateRight => "ROR",
+            Self::ClearCarry => "CLC",
+            Self::ClearDecimal => "CLD",
+            Self::ClearInterruptDisable => "CLI",
+            Self::ClearOverflow => "CLV",
+            Self::SetCarry => "SEC",
+            Self::SetDecimal => "SED",
+            Self::SetInterruptDisable => "SEI",
+            Self::CompareWithAccumulator => "CMP",
+            Self::CompareWithX => "CPX",
+            Self::CompareWithY => "CPY",
+            Self::BranchOnCarryClear => "BCC",
+            Self::BranchOnCarrySet => "BCS",
+            Self::BranchOnEqual => "BEQ",
+            Self::BranchOnMinus => "BMI",
+            Self::BranchOnNotEqual => "BNE",
+            Self::BranchOnPlus => "BPL",
+            Self::BranchOnOverflowClear => "BVC",
+            Self::BranchOnOverflowSet => "BVS",
+            Self::Jump => "JMP",
+            Self::JumpSubroutine => "JSR",
+            Self::ReturnFromSubroutine => "RTS",
+            Self::SoftwareInterrupt => "BRK",
+            Self::ReturnFromInterrupt => "RTI",
+            Self::BitTest => "BIT",
+            Self::NoOperation => "NOP",
+        }
+    }
+}
+
+/// the canonical three-letter mnemonic
+impl fmt::Display for Operations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.mnemonic())
     }
 }
 
@@ -105,4 +210,68 @@ impl PartialEq for Instruction {
     fn eq(&self, other: &Self) -> bool {
         self.operation == other.operation && self.addressing == other.addressing && self.cycle_count == other.cycle_count
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn operations_display_as_mnemonics() {
+        let expected = [
+            (Operations::LoadAccumulator, "LDA"),
+            (Operations::StoreX, "STX"),
+            (Operations::TransferXToStackPointer, "TXS"),
+            (Operations::PullStatusRegister, "PLP"),
+            (Operations::AddWithCarry, "ADC"),
+            (Operations::ExclusiveOrWithAccumulator, "EOR"),
+            (Operations::RotateRight, "ROR"),
+            (Operations::CompareWithY, "CPY"),
+            (Operations::BranchOnCarryClear, "BCC"),
+            (Operations::BranchOnCarrySet, "BCS"),
+            (Operations::BranchOnEqual, "BEQ"),
+            (Operations::BranchOnMinus, "BMI"),
+            (Operations::BranchOnNotEqual, "BNE"),
+            (Operations::BranchOnPlus, "BPL"),
+            (Operations::BranchOnOverflowClear, "BVC"),
+            (Operations::BranchOnOverflowSet, "BVS"),
+            (Operations::ClearCarry, "CLC"),
+            (Operations::ClearDecimal, "CLD"),
+            (Operations::ClearInterruptDisable, "CLI"),
+            (Operations::ClearOverflow, "CLV"),
+            (Operations::SetCarry, "SEC"),
+            (Operations::SetDecimal, "SED"),
+            (Operations::SetInterruptDisable, "SEI"),
+            (Operations::JumpSubroutine, "JSR"),
+            (Operations::SoftwareInterrupt, "BRK"),
+            (Operations::ReturnFromInterrupt, "RTI"),
+            (Operations::NoOperation, "NOP"),
+        ];
+
+        for (operation, mnemonic) in expected {
+            assert_eq!(mnemonic, operation.to_string());
+        }
+    }
+
+    #[test]
+    fn operations_work_as_set_keys() {
+        let mut seen = HashSet::new();
+        seen.insert(Operations::LoadAccumulator);
+        seen.insert(Operations::StoreAccumulator);
+        seen.insert(Operations::LoadAccumulator);
+
+        assert_eq!(2, seen.len());
+        assert!(seen.contains(&Operations::StoreAccumulator));
+        assert!(!seen.contains(&Operations::LoadX));
+    }
+
+    #[test]
+    fn addressing_compares_operands() {
+        assert_eq!(Addressing::Zeropage(0x10), Addressing::Zeropage(0x10));
+        assert_ne!(Addressing::Zeropage(0x10), Addressing::Zeropage(0x11));
+        assert_ne!(Addressing::Zeropage(0x10), Addressing::Immediate(0x10));
+        assert_ne!(Addressing::IndexedAbsolute(0x2000, 0x01), Addressing::IndexedAbsolute(0x2000, 0x02));
+    }
+}
@@ -0,0 +1,495 @@
+use super::*;
+
+/// where the handlers' addresses are
+const NMI_VECTOR : u16 = 0xfffa;
+const RESET_VECTOR : u16 = 0xfffc;
+const IRQ_VECTOR : u16 = 0xfffe;
+
+/// B only exists in the copy of the status register pushed to the stack
+const BREAK_BIT : u8 = 1 << (CpuFlags::BreakFlag as u8);
+const UNUSED_BIT : u8 = 1 << (CpuFlags::_Unused as u8);
+
+impl<B: Bus> Cpu<B> {
+    /// pushes a byte onto the stack page
+    pub(super) fn push(&mut self, value : u8) -> Result<(), CpuError> {
+        if self.strict_stack && self.sp == 0x00 {
+            return Err(CpuError::StackOverflow);
+        }
+        self.write(0x0100 | self.sp as u16, value)?;
+        self.sp = self.sp.wrapping_sub(1);
+        Ok(())
+    }
+
+    pub(super) fn pull(&mut self) -> Result<u8, CpuError> {
+        if self.strict_stack && self.sp == 0xff {
+            return Err(CpuError::StackUnderflow);
+        }
+        self.sp = self.sp.wrapping_add(1);
+        self.read(0x0100 | self.sp as u16)
+    }
+
+    /// with the strict stack on, fails before pushing a frame of `bytes` that wouldn't fit,
+    /// so an interrupt or JSR that can't go through leaves the stack as it was
+    fn check_room(&self, bytes : u8) -> Result<(), CpuError> {
+        if self.strict_stack && self.sp < bytes {
+            return Err(CpuError::StackOverflow);
+        }
+        Ok(())
+    }
+
+    /// the same for pulling a frame of `bytes` with RTI or RTS
+    fn check_frame(&self, bytes : u8) -> Result<(), CpuError> {
+        if self.strict_stack && self.sp as u16 + bytes as u16 > 0xff {
+            return Err(CpuError::StackUnderflow);
+        }
+        Ok(())
+    }
+
+    /// the status register as PHP and BRK push it, or as IRQ and NMI do when `brk` is false
+    fn pushed_status(&self, brk : bool) -> u8 {
+        if brk {
+            self.status() | BREAK_BIT
+        } else {
+            self.status() & !BREAK_BIT
+        }
+    }
+
+    /// loads a status register pulled by PLP or RTI, which can't change B or the unused bit
+    fn set_pulled_status(&mut self, value : u8) {
+        self.sr = (value & !(BREAK_BIT | UNUSED_BIT)) | (self.sr & (BREAK_BIT | UNUSED_BIT));
+    }
+
+    /// pushes the return address and the status, then jumps through `vector`
+    fn interrupt(&mut self, return_addr : u16, vector : u16, brk : bool) -> Result<(), CpuError> {
+        self.check_room(3)?;
+        let [low, high] = return_addr.to_le_bytes();
+        self.push(high)?;
+        self.push(low)?;
+        self.push(self.pushed_status(brk))?;
+
+        self.set_flag(CpuFlags::InterruptDisable);
+        self.delayed_interrupt_disable = None; // the handler's I isn't delayed
+        self.pc = self.load_little_endian_u16(vector)?;
+        Ok(())
+    }
+
+    /// where reset jumps to
+    pub fn reset_vector(&self) -> Result<u16, OutOfRangeError> {
+        self.load_little_endian_u16(RESET_VECTOR)
+    }
+
+    /// where IRQ and BRK jump to
+    pub fn irq_vector(&self) -> Result<u16, OutOfRangeError> {
+        self.load_little_endian_u16(IRQ_VECTOR)
+    }
+
+    pub fn nmi_vector(&self) -> Result<u16, OutOfRangeError> {
+        self.load_little_endian_u16(NMI_VECTOR)
+    }
+
+    /// enters the IRQ handler now unless masked, returning the cycles that took; use
+    /// `set_irq_line` to hold the line. right after a CLI, SEI or PLP the previous I flag
+    /// decides, and the next `step` still polls with it
+    pub fn irq(&mut self) -> Result<u8, CpuError> {
+        if self.irq_masked() {
+            return Ok(0);
+        }
+
+        self.interrupt(self.pc, IRQ_VECTOR, false)?;
+        self.consume(7);
+        Ok(7)
+    }
+
+    /// enters the IRQ handler if the line is up and the poll sees interrupts enabled,
+    /// returning the cycles that took
+    pub(super) fn poll_irq(&mut self) -> Result<Option<u8>, CpuError> {
+        let disabled = self.irq_masked();
+        self.delayed_interrupt_disable = None;
+        if !self.irq_line || disabled {
+            return Ok(None);
+        }
+
+        self.interrupt(self.pc, IRQ_VECTOR, false)?;
+        self.consume(7);
+        Ok(Some(7))
+    }
+
+    /// the I flag as the interrupt poll sees it, which lags one instruction behind CLI, SEI and PLP
+    fn irq_masked(&self) -> bool {
+        self.delayed_interrupt_disable
+            .unwrap_or_else(|| self.get_flag(CpuFlags::InterruptDisable))
+    }
+
+    /// CLI, SEI and PLP change I too late for the poll at the end of their own instruction,
+    /// so an IRQ pending across a CLI waits one more instruction. RTI isn't delayed
+    pub(super) fn delay_interrupt_disable(&mut self, operation : Operations, sr_before : u8) {
+        if matches!(operation, Operations::ClearInterruptDisable
+                | Operations::SetInterruptDisable
+                | Operations::PullStatusRegister) {
+            let mask = 1 << (CpuFlags::InterruptDisable as u8);
+            self.delayed_interrupt_disable = Some(sr_before & mask != 0);
+        }
+    }
+
+    /// the non-maskable interrupt, entered even with interrupts disabled
+    pub fn nmi(&mut self) -> Result<u8, CpuError> {
+        self.interrupt(self.pc, NMI_VECTOR, false)?;
+        self.consume(7);
+        Ok(7)
+    }
+
+    /// BRK skips the byte after the opcode, so the handler returns past it
+    pub(super) fn software_interrupt(&mut self) -> Result<(), CpuError> {
+        self.interrupt(self.pc.wrapping_add(1), IRQ_VECTOR, true)
+    }
+
+    pub(super) fn push_status(&mut self) -> Result<(), CpuError> {
+        self.push(self.pushed_status(true))
+    }
+
+    pub(super) fn pull_status(&mut self) -> Result<(), CpuError> {
+        let value = self.pull()?;
+        self.set_pulled_status(value);
+        Ok(())
+    }
+
+    pub(super) fn return_from_interrupt(&mut self) -> Result<(), CpuError> {
+        self.check_frame(3)?;
+        self.pull_status()?;
+        let low = self.pull()?;
+        let high = self.pull()?;
+        self.pc = u16::from_le_bytes([low, high]);
+        Ok(())
+    }
+
+    /// JSR pushes the address of its own last byte, not of the next instruction
+    pub(super) fn jump_to_subroutine(&mut self, target : u16) -> Result<(), CpuError> {
+        self.check_room(2)?;
+        let [low, high] = self.pc.wrapping_sub(1).to_le_bytes();
+        self.push(high)?;
+        self.push(low)?;
+        self.pc = target;
+        Ok(())
+    }
+
+    pub(super) fn return_from_subroutine(&mut self) -> Result<(), CpuError> {
+        self.check_frame(2)?;
+        let low = self.pull()?;
+        let high = self.pull()?;
+        self.pc = u16::from_le_bytes([low, high]).wrapping_add(1);
+        Ok(())
+    }
+
+    /// pushes `bytes` in order, so a test can build a return frame before running RTI or RTS.
+    /// the last byte ends up on top of the stack
+    pub fn push_for_test(&mut self, bytes : &[u8]) -> Result<(), CpuError> {
+        for &byte in bytes {
+            self.push(byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu_with_vectors(rom : &[u8]) -> Cpu {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, rom).unwrap();
+        mem.store_word(NMI_VECTOR, 0x3000).unwrap();
+        mem.store_word(IRQ_VECTOR, 0x2000).unwrap();
+        Cpu::new(mem)
+    }
+
+    #[test]
+    fn brk_pushes_b_set() {
+        let mut cpu = cpu_with_vectors(&[0x00, 0xea]);
+        cpu.sr = 0b1100_0001;
+
+        assert_eq!(7, cpu.step().unwrap());
+        assert_eq!(0x2000, cpu.pc);
+        assert_eq!(0xfc, cpu.sp);
+        assert_eq!(0x10, cpu.memory.load(0x01ff).unwrap());
+        assert_eq!(0x02, cpu.memory.load(0x01fe).unwrap());
+        assert_eq!(0b1111_0001, cpu.memory.load(0x01fd).unwrap());
+        assert!(cpu.get_flag(CpuFlags::InterruptDisable));
+    }
+
+    #[test]
+    fn irq_pushes_b_clear() {
+        let mut cpu = cpu_with_vectors(&[0xea]);
+        cpu.sr = 0b1101_0001;
+
+        assert_eq!(7, cpu.irq().unwrap());
+        assert_eq!(0x2000, cpu.pc);
+        assert_eq!(0x10, cpu.memory.load(0x01ff).unwrap());
+        assert_eq!(0x00, cpu.memory.load(0x01fe).unwrap());
+        assert_eq!(0b1110_0001, cpu.memory.load(0x01fd).unwrap());
+
+        // masked now
+        assert_eq!(0, cpu.irq().unwrap());
+        assert_eq!(0xfc, cpu.sp);
+
+        cpu.nmi().unwrap();
+        assert_eq!(0x3000, cpu.pc);
+        assert_eq!(0b1110_0101, cpu.memory.load(0x01fa).unwrap());
+    }
+
+    #[test]
+    fn irq_pending_across_cli_waits_one_instruction() {
+        let rom = [
+            0x58, // CLI
+            0xea, // NOP
+            0xea  // NOP
+        ];
+        let mut cpu = cpu_with_vectors(&rom);
+        cpu.set_flag(CpuFlags::InterruptDisable);
+        cpu.set_irq_line(true);
+
+        assert_eq!(2, cpu.step().unwrap());
+        assert_eq!(0x1001, cpu.pc);
+        assert!(!cpu.get_flag(CpuFlags::InterruptDisable));
+
+        // the poll after CLI still saw I set, so the NOP runs first
+        assert_eq!(2, cpu.step().unwrap());
+        assert_eq!(0x1002, cpu.pc);
+
+        assert_eq!(7, cpu.step().unwrap());
+        assert_eq!(0x2000, cpu.pc);
+        assert_eq!(0x10, cpu.memory.load(0x01ff).unwrap());
+        assert_eq!(0x02, cpu.memory.load(0x01fe).unwrap());
+
+        // the handler runs with I set, so the line being still up doesn't matter
+        cpu.memory.load_rom(0x2000, &[0xea]).unwrap();
+        assert_eq!(2, cpu.step().unwrap());
+        assert_eq!(0x2001, cpu.pc);
+    }
+
+    #[test]
+    fn irq_right_after_cli_is_ignored() {
+        let mut cpu = cpu_with_vectors(&[0x58, 0xea]); // CLI, NOP
+        cpu.set_flag(CpuFlags::InterruptDisable);
+
+        cpu.step().unwrap();
+        assert_eq!(0, cpu.irq().unwrap());
+        assert_eq!(0x1001, cpu.pc);
+
+        cpu.step().unwrap();
+        assert_eq!(7, cpu.irq().unwrap());
+        assert_eq!(0x2000, cpu.pc);
+    }
+
+    #[test]
+    fn irq_after_cli_leaves_the_delay_to_the_next_poll() {
+        let mut cpu = cpu_with_vectors(&[0x58, 0xea, 0xea]); // CLI, NOP, NOP
+        cpu.set_flag(CpuFlags::InterruptDisable);
+        cpu.set_irq_line(true);
+
+        cpu.step().unwrap();
+        assert_eq!(0, cpu.irq().unwrap());
+
+        // the poll before the NOP still sees I set
+        assert_eq!(2, cpu.step().unwrap());
+        assert_eq!(0x1002, cpu.pc);
+        assert_eq!(7, cpu.step().unwrap());
+        assert_eq!(0x2000, cpu.pc);
+    }
+
+    #[test]
+    fn power_on_drops_the_irq_line_and_the_cli_snapshot() {
+        let mut cpu = cpu_with_vectors(&[0x58]); // CLI
+        cpu.set_flag(CpuFlags::InterruptDisable);
+        cpu.step().unwrap();
+        cpu.set_irq_line(true);
+
+        cpu.power_on().unwrap();
+        assert!(!cpu.irq_line);
+        assert_eq!(None, cpu.delayed_interrupt_disable);
+    }
+
+    #[test]
+    fn irq_is_taken_right_after_sei() {
+        let mut cpu = cpu_with_vectors(&[0x78, 0xea]); // SEI, NOP
+
+        cpu.step().unwrap();
+        cpu.set_irq_line(true);
+
+        // the poll after SEI still saw I clear, so the IRQ gets in before the NOP
+        assert_eq!(7, cpu.step().unwrap());
+        assert_eq!(0x2000, cpu.pc);
+        assert_eq!(0x01, cpu.memory.load(0x01fe).unwrap());
+    }
+
+    #[test]
+    fn php_pushes_b_set_and_plp_ignores_it() {
+        let rom = [
+            0x08, // PHP
+            0x28  // PLP
+        ];
+        let mut cpu = cpu_with_vectors(&rom);
+        cpu.sr = 0b0000_0011;
+
+        cpu.step().unwrap();
+        assert_eq!(0b0011_0011, cpu.memory.load(0x01ff).unwrap());
+
+        cpu.memory.store(0x01ff, 0b1111_1100).unwrap();
+        cpu.step().unwrap();
+        assert_eq!(0b1100_1100, cpu.sr);
+        assert_eq!(0xff, cpu.sp);
+    }
+
+    #[test]
+    fn unused_bit_reads_as_set_after_plp() {
+        let mut cpu = cpu_with_vectors(&[0x28]); // PLP
+        cpu.memory.store(0x01ff, 0b1000_0001).unwrap();
+        cpu.sp = 0xfe;
+
+        cpu.step().unwrap();
+        assert_eq!(0b1010_0001, cpu.sr());
+        assert_eq!(0b1010_0001, cpu.state().sr);
+        assert!(cpu.flag(CpuFlags::_Unused));
+    }
+
+    #[test]
+    fn rti_returns_past_brk() {
+        let mut cpu = cpu_with_vectors(&[0x00, 0xff, 0xea]);
+        cpu.memory.load_rom(0x2000, &[0x40]).unwrap(); // RTI
+        cpu.sr = 0b1000_0000;
+
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(0x1002, cpu.pc);
+        assert_eq!(0b1000_0000, cpu.sr);
+        assert_eq!(0xff, cpu.sp);
+    }
+
+    #[test]
+    fn rti_restores_a_pushed_frame() {
+        let mut cpu = cpu_with_vectors(&[0x40]); // RTI
+        // return address high, low, then the status with B and the unused bit clear
+        cpu.push_for_test(&[0x23, 0x45, 0b1100_0011]).unwrap();
+
+        assert_eq!(6, cpu.step().unwrap());
+        assert_eq!(0x2345, cpu.pc);
+        assert_eq!(0b1110_0011, cpu.sr());
+        assert!(!cpu.flag(CpuFlags::BreakFlag));
+        assert_eq!(0xff, cpu.sp);
+    }
+
+    #[test]
+    fn jsr_and_rts_round_trip() {
+        let rom = [
+            0x20, 0x00, 0x11, // JSR $1100
+            0xea              // NOP
+        ];
+        let mut cpu = cpu_with_vectors(&rom);
+        cpu.memory.load_rom(0x1100, &[0x60]).unwrap(); // RTS
+
+        assert_eq!(6, cpu.step().unwrap());
+        assert_eq!(0x1100, cpu.pc);
+        assert_eq!(0x10, cpu.memory.load(0x01ff).unwrap());
+        assert_eq!(0x02, cpu.memory.load(0x01fe).unwrap());
+
+        assert_eq!(6, cpu.step().unwrap());
+        assert_eq!(0x1003, cpu.pc);
+        assert_eq!(0xff, cpu.sp);
+    }
+
+    #[test]
+    fn rts_returns_from_a_pushed_address() {
+        let mut cpu = cpu_with_vectors(&[0x60]); // RTS
+        cpu.push_for_test(&[0x20, 0xff]).unwrap();
+
+        cpu.step().unwrap();
+        assert_eq!(0x2100, cpu.pc);
+    }
+
+    #[test]
+    fn stack_wraps_around_by_default() {
+        let mut cpu = cpu_with_vectors(&[0x48, 0x68]); // PHA, PLA
+        cpu.sp = 0x00;
+        cpu.a = 0x42;
+
+        cpu.step().unwrap();
+        assert_eq!(0x42, cpu.memory.load(0x0100).unwrap());
+        assert_eq!(0xff, cpu.sp);
+
+        cpu.a = 0x00;
+        cpu.step().unwrap();
+        assert_eq!(0x42, cpu.a);
+        assert_eq!(0x00, cpu.sp);
+    }
+
+    #[test]
+    fn strict_stack_reports_overflow_and_underflow() {
+        let mut cpu = cpu_with_vectors(&[0x48, 0x60]); // PHA, RTS
+        cpu.set_strict_stack(true);
+        cpu.sp = 0x00;
+
+        assert!(matches!(cpu.step(), Err(CpuError::StackOverflow)));
+        assert_eq!(0x00, cpu.sp);
+
+        cpu.sp = 0xff;
+        assert!(matches!(cpu.step(), Err(CpuError::StackUnderflow)));
+        assert_eq!(0xff, cpu.sp);
+    }
+
+    #[test]
+    fn strict_stack_leaves_no_half_frames() {
+        let mut cpu = cpu_with_vectors(&[0x40, 0x20, 0x00, 0x20]); // RTI, JSR $2000
+        cpu.set_strict_stack(true);
+        cpu.memory.store(0x0102, 0x55).unwrap();
+        cpu.sp = 0x02;
+
+        assert!(matches!(cpu.irq(), Err(CpuError::StackOverflow)));
+        assert!(matches!(cpu.nmi(), Err(CpuError::StackOverflow)));
+        assert_eq!(0x02, cpu.sp);
+        assert_eq!(0x1000, cpu.pc);
+        assert_eq!(0x55, cpu.memory.load(0x0102).unwrap());
+        assert!(!cpu.get_flag(CpuFlags::InterruptDisable));
+
+        // two bytes left, enough for JSR but not for an interrupt
+        cpu.pc = 0x1001;
+        assert!(cpu.step().is_ok());
+        assert_eq!(0x2000, cpu.pc);
+        assert_eq!(0x00, cpu.sp);
+
+        cpu.sp = 0xfd;
+        cpu.pc = 0x1000;
+        assert!(matches!(cpu.step(), Err(CpuError::StackUnderflow)));
+        assert_eq!(0xfd, cpu.sp);
+    }
+
+    #[test]
+    fn reads_the_vectors() {
+        let mut cpu = cpu_with_vectors(&[0xea]);
+        cpu.memory.store_word(RESET_VECTOR, 0x1000).unwrap();
+
+        assert_eq!(0x1000, cpu.reset_vector().unwrap());
+        assert_eq!(0x2000, cpu.irq_vector().unwrap());
+        assert_eq!(0x3000, cpu.nmi_vector().unwrap());
+    }
+
+    #[test]
+    fn irq_after_reset_pushes_below_0xfd() {
+        let mut cpu = cpu_with_vectors(&[0x58, 0xea]); // CLI, NOP
+        cpu.memory.store_word(RESET_VECTOR, 0x1000).unwrap();
+        cpu.reset().unwrap();
+        assert_eq!(0xfd, cpu.sp);
+
+        // the IRQ only gets through after the instruction following CLI
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        cpu.irq().unwrap();
+        assert_eq!(0xfa, cpu.sp);
+        assert_eq!(0x10, cpu.memory.load(0x01fd).unwrap());
+        assert_eq!(0x02, cpu.memory.load(0x01fc).unwrap());
+        assert_eq!(0b0010_0000, cpu.memory.load(0x01fb).unwrap());
+
+        cpu.memory.load_rom(0x2000, &[0x40]).unwrap(); // RTI
+        cpu.step().unwrap();
+        assert_eq!(0x1002, cpu.pc);
+        assert_eq!(0xfd, cpu.sp);
+    }
+}
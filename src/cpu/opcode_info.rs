@@ -0,0 +1,262 @@
+use super::*;
+
+/// what the decoder knows about an opcode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OpcodeInfo {
+    pub operation : Operations,
+    /// the three-letter mnemonic, like `LDA`
+    pub mnemonic : &'static str,
+    pub mode : AddressingMode,
+    /// opcode plus operands
+    pub bytes : u16,
+    /// without the page-cross or branch penalties
+    pub cycles : u16
+}
+
+/// looks an opcode up in the decoder's table, no cpu needed.
+/// None for the undocumented opcodes
+pub fn opcode_info(opcode : u8) -> Option<OpcodeInfo> {
+    let (operation, mode, cycles) = match opcode {
+        0x00 => (Operations::SoftwareInterrupt, AddressingMode::Implied, 7), // BRK
+        0x48 => (Operations::PushAccumulator, AddressingMode::Implied, 3), // PHA
+        0x68 => (Operations::PullAccumulator, AddressingMode::Implied, 4), // PLA
+        0x28 => (Operations::PullStatusRegister, AddressingMode::Implied, 4), // PLP
+        0x09 => (Operations::InclusiveOrWithAccumulator, AddressingMode::Immediate, 2), // ORA immediate
+        0x05 => (Operations::InclusiveOrWithAccumulator, AddressingMode::Zeropage, 3), // ORA zeropage
+        0x15 => (Operations::InclusiveOrWithAccumulator, AddressingMode::IndexedZeropage, 4), // ORA indexed zeropage
+        0x0d => (Operations::InclusiveOrWithAccumulator, AddressingMode::Absolute, 4), // ORA absolute
+        0x1d => (Operations::InclusiveOrWithAccumulator, AddressingMode::IndexedAbsolute, 4), // ORA absolute,X
+        0x19 => (Operations::InclusiveOrWithAccumulator, AddressingMode::IndexedAbsolute, 4), // ORA absolute,Y
+        0x01 => (Operations::InclusiveOrWithAccumulator, AddressingMode::PreindexedIndirect, 6), // ORA (indirect,X)
+        0x11 => (Operations::InclusiveOrWithAccumulator, AddressingMode::PostindexedIndirect, 5), // ORA (indirect), Y
+        0x0a => (Operations::ArithmeticShiftLeft, AddressingMode::Implied, 2), // ASL accumulator (implied)
+        0x06 => (Operations::ArithmeticShiftLeft, AddressingMode::Zeropage, 5), // ASL zeropage
+        0x16 => (Operations::ArithmeticShiftLeft, AddressingMode::IndexedZeropage, 6), // ASL zeropage,X
+        0x0e => (Operations::ArithmeticShiftLeft, AddressingMode::Absolute, 6), // ASL absolute
+        0x1e => (Operations::ArithmeticShiftLeft, AddressingMode::IndexedAbsolute, 7), // ASL absolute, X
+        0x08 => (Operations::PushStatusRegister, AddressingMode::Implied, 3), // PHP implied
+        0x10 => (Operations::BranchOnPlus, AddressingMode::RelativeAddress, 2), // BPL relative
+        0x18 => (Operations::ClearCarry, AddressingMode::Implied, 2), // CLC implied
+        0x20 => (Operations::JumpSubroutine, AddressingMode::Absolute, 6), // JSR absolute
+        0x29 => (Operations::AndWithAccumulator, AddressingMode::Immediate, 2), // AND immediate
+        0x25 => (Operations::AndWithAccumulator, AddressingMode::Zeropage, 3), // AND zeropage
+        0x35 => (Operations::AndWithAccumulator, AddressingMode::IndexedZeropage, 4), // AND zeropage,X
+        0x2d => (Operations::AndWithAccumulator, AddressingMode::Absolute, 4), // AND absolute
+        0x3d => (Operations::AndWithAccumulator, AddressingMode::IndexedAbsolute, 4), // AND absolute,X
+        0x39 => (Operations::AndWithAccumulator, AddressingMode::IndexedAbsolute, 4), // AND absolute,Y
+        0x21 => (Operations::AndWithAccumulator, AddressingMode::PreindexedIndirect, 6), // AND (indirect,X)
+        0x31 => (Operations::AndWithAccumulator, AddressingMode::PostindexedIndirect, 5), // AND (indirect),Y
+        0x24 => (Operations::BitTest, AddressingMode::Zeropage, 3), // BIT zeropage
+        0x2c => (Operations::BitTest, AddressingMode::Absolute, 4), // BIT absolute
+        0x2a => (Operations::RotateLeft, AddressingMode::Implied, 2), // ROL accumulator
+        0x26 => (Operations::RotateLeft, AddressingMode::Zeropage, 5), // ROL zeropage
+        0x36 => (Operations::RotateLeft, AddressingMode::IndexedZeropage, 6), // ROL zeropage, X
+        0x2e => (Operations::RotateLeft, AddressingMode::Absolute, 6), // ROL absolute
+        0x3e => (Operations::RotateLeft, AddressingMode::IndexedAbsolute, 7), // ROL absolute, X
+        0x30 => (Operations::BranchOnMinus, AddressingMode::RelativeAddress, 2), // BMI relative
+        0x38 => (Operations::SetCarry, AddressingMode::Implied, 2), // SEC
+        0x40 => (Operations::ReturnFromInterrupt, AddressingMode::Implied, 6), // RTI
+        0x49 => (Operations::ExclusiveOrWithAccumulator, AddressingMode::Immediate, 2), // EOR immediate
+        0x45 => (Operations::ExclusiveOrWithAccumulator, AddressingMode::Zeropage, 3), // EOR zeropage
+        0x55 => (Operations::ExclusiveOrWithAccumulator, AddressingMode::IndexedZeropage, 4), // EOR zeropage, X
+        0x4d => (Operations::ExclusiveOrWithAccumulator, AddressingMode::Absolute, 4), // EOR absolute
+        0x5d => (Operations::ExclusiveOrWithAccumulator, AddressingMode::IndexedAbsolute, 4), // EOR absolute,X
+        0x59 => (Operations::ExclusiveOrWithAccumulator, AddressingMode::IndexedAbsolute, 4), // EOR absolute,Y
+        0x41 => (Operations::ExclusiveOrWithAccumulator, AddressingMode::PreindexedIndirect, 6), // EOR (indirect,X)
+        0x51 => (Operations::ExclusiveOrWithAccumulator, AddressingMode::PostindexedIndirect, 5), // EOR (indirect),Y
+        0x4a => (Operations::LogicalShiftRight, AddressingMode::Implied, 2), // LSR accumulator
+        0x46 => (Operations::LogicalShiftRight, AddressingMode::Zeropage, 5), // LSR zeropage
+        0x56 => (Operations::LogicalShiftRight, AddressingMode::IndexedZeropage, 6), // LSR zeropage,X
+        0x4e => (Operations::LogicalShiftRight, AddressingMode::Absolute, 6), // LSR absolute
+        0x5e => (Operations::LogicalShiftRight, AddressingMode::IndexedAbsolute, 7), // LSR absolute,X
+        0x4c => (Operations::Jump, AddressingMode::Absolute, 3), // JMP absolute
+        0x6c => (Operations::Jump, AddressingMode::Indirect, 5), // JMP indirect
+        0x50 => (Operations::BranchOnOverflowClear, AddressingMode::RelativeAddress, 2), // BVC relative
+        0x58 => (Operations::ClearInterruptDisable, AddressingMode::Implied, 2), // CLI
+        0x60 => (Operations::ReturnFromSubroutine, AddressingMode::Implied, 6), // RTS
+        0x69 => (Operations::AddWithCarry, AddressingMode::Immediate, 2), // ADC immediate
+        0x65 => (Operations::AddWithCarry, AddressingMode::Zeropage, 3), // ADC zeropage
+        0x75 => (Operations::AddWithCarry, AddressingMode::IndexedZeropage, 4), // ADC zeropage,X
+        0x6d => (Operations::AddWithCarry, AddressingMode::Absolute, 4), // ADC absolute
+        0x7d => (Operations::AddWithCarry, AddressingMode::IndexedAbsolute, 4), // ADC absolute,X
+        0x79 => (Operations::AddWithCarry, AddressingMode::IndexedAbsolute, 4), // ADC absolute,Y
+        0x61 => (Operations::AddWithCarry, AddressingMode::PreindexedIndirect, 6), // ADC (indirect,X)
+        0x71 => (Operations::AddWithCarry, AddressingMode::PostindexedIndirect, 5), // ADC (indirect),Y
+        0x6a => (Operations::RotateRight, AddressingMode::Implied, 2), // ROR accumulator
+        0x66 => (Operations::RotateRight, AddressingMode::Zeropage, 5), // ROR zeropage
+        0x76 => (Operations::RotateRight, AddressingMode::IndexedZeropage, 6), // ROR zeropage,X
+        0x6e => (Operations::RotateRight, AddressingMode::Absolute, 6), // ROR absolute
+        0x7e => (Operations::RotateRight, AddressingMode::IndexedAbsolute, 7), // ROR absolute,X
+        0x70 => (Operations::BranchOnOverflowSet, AddressingMode::RelativeAddress, 2), // BVS relative
+        0x78 => (Operations::SetInterruptDisable, AddressingMode::Implied, 2), // SEI
+        0x85 => (Operations::StoreAccumulator, AddressingMode::Zeropage, 3), // STA zeropage
+        0x95 => (Operations::StoreAccumulator, AddressingMode::IndexedZeropage, 4), // STA zeropage,X
+        0x8d => (Operations::StoreAccumulator, AddressingMode::Absolute, 4), // STA absolute
+        0x9d => (Operations::StoreAccumulator, AddressingMode::IndexedAbsolute, 5), // STA absolute,X
+        0x99 => (Operations::StoreAccumulator, AddressingMode::IndexedAbsolute, 5), // STA absolute,Y
+        0x81 => (Operations::StoreAccumulator, AddressingMode::PreindexedIndirect, 6), // STA (indirect,X)
+        0x91 => (Operations::StoreAccumulator, AddressingMode::PostindexedIndirect, 6), // STA (indirect),Y
+        0x84 => (Operations::StoreY, AddressingMode::Zeropage, 3), // STY zeropage
+        0x94 => (Operations::StoreY, AddressingMode::IndexedZeropage, 4), // STY zeropage,X
+        0x8c => (Operations::StoreY, AddressingMode::Absolute, 4), // STY absolute
+        0x86 => (Operations::StoreX, AddressingMode::Zeropage, 3), // STX zeropage
+        0x96 => (Operations::StoreX, AddressingMode::IndexedZeropage, 4), // STX zeropage,Y
+        0x8e => (Operations::StoreX, AddressingMode::Absolute, 4), // STX absolute
+        0x88 => (Operations::DecrementY, AddressingMode::Implied, 2), // DEY
+        0x8a => (Operations::TransferXToAccumulator, AddressingMode::Implied, 2), // TXA
+        0x90 => (Operations::BranchOnCarryClear, AddressingMode::RelativeAddress, 2), // BCC relative
+        0x98 => (Operations::TransferYToAccumulator, AddressingMode::Implied, 2), // TYA
+        0x9a => (Operations::TransferXToStackPointer, AddressingMode::Implied, 2), // TXS
+        0xa0 => (Operations::LoadY, AddressingMode::Immediate, 2), // LDY immediate
+        0xa4 => (Operations::LoadY, AddressingMode::Zeropage, 3), // LDY zeropage
+        0xb4 => (Operations::LoadY, AddressingMode::IndexedZeropage, 4), // LDY zeropage,X
+        0xac => (Operations::LoadY, AddressingMode::Absolute, 4), // LDY absolute
+        0xbc => (Operations::LoadY, AddressingMode::IndexedAbsolute, 4), // LDY absolute,X
+        0xa2 => (Operations::LoadX, AddressingMode::Immediate, 2), // LDX immediate
+        0xa6 => (Operations::LoadX, AddressingMode::Zeropage, 3), // LDX zeropage
+        0xb6 => (Operations::LoadX, AddressingMode::IndexedZeropage, 4), // LDX zeropage,Y
+        0xae => (Operations::LoadX, AddressingMode::Absolute, 4), // LDX absolute
+        0xbe => (Operations::LoadX, AddressingMode::IndexedAbsolute, 4), // LDX absolute,Y
+        0xa9 => (Operations::LoadAccumulator, AddressingMode::Immediate, 2), // LDA immediate
+        0xa5 => (Operations::LoadAccumulator, AddressingMode::Zeropage, 3), // LDA zeropage
+        0xb5 => (Operations::LoadAccumulator, AddressingMode::IndexedZeropage, 4), // LDA zeropage,X
+        0xad => (Operations::LoadAccumulator, AddressingMode::Absolute, 4), // LDA absolute
+        0xbd => (Operations::LoadAccumulator, AddressingMode::IndexedAbsolute, 4), // LDA absolute,X
+        0xb9 => (Operations::LoadAccumulator, AddressingMode::IndexedAbsolute, 4), // LDA absolute,Y
+        0xa1 => (Operations::LoadAccumulator, AddressingMode::PreindexedIndirect, 6), // LDA (indirect,X)
+        0xb1 => (Operations::LoadAccumulator, AddressingMode::PostindexedIndirect, 5), // LDA (indirect), Y
+        0xa8 => (Operations::TransferAccumulatorToY, AddressingMode::Implied, 2), // TAY
+        0xaa => (Operations::TransferAccumulatorToX, AddressingMode::Implied, 2), // TAX
+        0xb0 => (Operations::BranchOnCarrySet, AddressingMode::RelativeAddress, 2), // BCS
+        0xb8 => (Operations::ClearOverflow, AddressingMode::Implied, 2), // CLV
+        0xba => (Operations::TransferStackPointerToX, AddressingMode::Implied, 2), // TSX
+        0xc0 => (Operations::CompareWithY, AddressingMode::Immediate, 2), // CPY immediate
+        0xc4 => (Operations::CompareWithY, AddressingMode::Zeropage, 3), // CPY zeropage
+        0xcc => (Operations::CompareWithY, AddressingMode::Absolute, 4), // CPY absolute
+        0xc9 => (Operations::CompareWithAccumulator, AddressingMode::Immediate, 2), // CMP immediate
+        0xc5 => (Operations::CompareWithAccumulator, AddressingMode::Zeropage, 3), // CMP zeropage
+        0xd5 => (Operations::CompareWithAccumulator, AddressingMode::IndexedZeropage, 4), // CMP zeropage,X
+        0xcd => (Operations::CompareWithAccumulator, AddressingMode::Absolute, 4), // CMP absolute
+        0xdd => (Operations::CompareWithAccumulator, AddressingMode::IndexedAbsolute, 4), // CMP absolute,X
+        0xd9 => (Operations::CompareWithAccumulator, AddressingMode::IndexedAbsolute, 4), // CMP absolute, Y
+        0xc1 => (Operations::CompareWithAccumulator, AddressingMode::PreindexedIndirect, 6), // CMP (indirect,X)
+        0xd1 => (Operations::CompareWithAccumulator, AddressingMode::PostindexedIndirect, 5), // CMP (indirect),Y
+        0xc6 => (Operations::DecrementMemory, AddressingMode::Zeropage, 5), // DEC zeropage
+        0xd6 => (Operations::DecrementMemory, AddressingMode::IndexedZeropage, 6), // DEC zeropage,X
+        0xce => (Operations::DecrementMemory, AddressingMode::Absolute, 6), // DEC absolute
+        0xde => (Operations::DecrementMemory, AddressingMode::IndexedAbsolute, 7), // DEC absolute,X
+        0xc8 => (Operations::IncrementY, AddressingMode::Implied, 2), // INY
+        0xca => (Operations::DecrementX, AddressingMode::Implied, 2), // DEX
+        0xd0 => (Operations::BranchOnNotEqual, AddressingMode::RelativeAddress, 2), // BNE
+        0xd8 => (Operations::ClearDecimal, AddressingMode::Implied, 2), // CLD
+        0xe0 => (Operations::CompareWithX, AddressingMode::Immediate, 2), // CPX immediate
+        0xe4 => (Operations::CompareWithX, AddressingMode::Zeropage, 3), // CPX zeropage
+        0xec => (Operations::CompareWithX, AddressingMode::Absolute, 4), // CPX absolute
+        0xe9 => (Operations::SubtractWithCarry, AddressingMode::Immediate, 2), // SBC immediate
+        0xe5 => (Operations::SubtractWithCarry, AddressingMode::Zeropage, 3), // SBC zeropage
+        0xf5 => (Operations::SubtractWithCarry, AddressingMode::IndexedZeropage, 4), // SBC zeropage,X
+        0xed => (Operations::SubtractWithCarry, AddressingMode::Absolute, 4), // SBC absolute
+        0xfd => (Operations::SubtractWithCarry, AddressingMode::IndexedAbsolute, 4), // SBC absolute,X
+        0xf9 => (Operations::SubtractWithCarry, AddressingMode::IndexedAbsolute, 4), // SBC absolute,Y
+        0xe1 => (Operations::SubtractWithCarry, AddressingMode::PreindexedIndirect, 6), // SBC (indirect,X)
+        0xf1 => (Operations::SubtractWithCarry, AddressingMode::PostindexedIndirect, 5), // SBC (indirect),Y
+        0xe6 => (Operations::IncrementMemory, AddressingMode::Zeropage, 5), // INC zeropage
+        0xf6 => (Operations::IncrementMemory, AddressingMode::IndexedZeropage, 6), // INC zeropage,X
+        0xee => (Operations::IncrementMemory, AddressingMode::Absolute, 6), // INC absolute
+        0xfe => (Operations::IncrementMemory, AddressingMode::IndexedAbsolute, 7), // INC absolute,X
+        0xe8 => (Operations::IncrementX, AddressingMode::Implied, 2), // INX
+        0xf0 => (Operations::BranchOnEqual, AddressingMode::RelativeAddress, 2), // BEQ relative
+        0xf8 => (Operations::SetDecimal, AddressingMode::Implied, 2), // SED
+        0xea => (Operations::NoOperation, AddressingMode::Implied, 2), // NOP
+        _ => return None
+    };
+
+    Some(OpcodeInfo {
+        operation,
+        mnemonic: operation.mnemonic(),
+        mode,
+        bytes: mode.instruction_size(),
+        cycles
+    })
+}
+
+/// the decoded addressing only carries the value of the index register, so the
+/// register itself has to be recovered from the opcode
+pub(crate) fn index_register(opcode : u8) -> char {
+    match opcode {
+        0x19 | 0x39 | 0x59 | 0x79 | 0x99 | 0xb9 | 0xbe | 0xd9 | 0xf9 // absolute,Y
+            | 0x96 | 0xb6 // zeropage,Y
+        => 'Y',
+        _ => 'X'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a bus holding nothing but an opcode at address 0
+    struct OpcodeBus(u8);
+
+    impl Bus for OpcodeBus {
+        fn load(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+            Ok(if addr == 0 { self.0 } else { 0x00 })
+        }
+
+        fn store(&mut self, _addr : u16, _v : u8) -> Result<(), OutOfRangeError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn describes_opcodes() {
+        assert_eq!(Some(OpcodeInfo {
+            operation: Operations::LoadAccumulator,
+            mnemonic: "LDA",
+            mode: AddressingMode::Immediate,
+            bytes: 2,
+            cycles: 2
+        }), opcode_info(0xa9));
+        assert_eq!(Some(OpcodeInfo {
+            operation: Operations::JumpSubroutine,
+            mnemonic: "JSR",
+            mode: AddressingMode::Absolute,
+            bytes: 3,
+            cycles: 6
+        }), opcode_info(0x20));
+        assert_eq!(None, opcode_info(0x02));
+    }
+
+    /// the 151 documented opcodes, everything else is illegal
+    const DOCUMENTED : [u8; 151] = [
+        0x00, 0x01, 0x05, 0x06, 0x08, 0x09, 0x0a, 0x0d, 0x0e,
+        0x10, 0x11, 0x15, 0x16, 0x18, 0x19, 0x1d, 0x1e,
+        0x20, 0x21, 0x24, 0x25, 0x26, 0x28, 0x29, 0x2a, 0x2c, 0x2d, 0x2e,
+        0x30, 0x31, 0x35, 0x36, 0x38, 0x39, 0x3d, 0x3e,
+        0x40, 0x41, 0x45, 0x46, 0x48, 0x49, 0x4a, 0x4c, 0x4d, 0x4e,
+        0x50, 0x51, 0x55, 0x56, 0x58, 0x59, 0x5d, 0x5e,
+        0x60, 0x61, 0x65, 0x66, 0x68, 0x69, 0x6a, 0x6c, 0x6d, 0x6e,
+        0x70, 0x71, 0x75, 0x76, 0x78, 0x79, 0x7d, 0x7e,
+        0x81, 0x84, 0x85, 0x86, 0x88, 0x8a, 0x8c, 0x8d, 0x8e,
+        0x90, 0x91, 0x94, 0x95, 0x96, 0x98, 0x99, 0x9a, 0x9d,
+        0xa0, 0xa1, 0xa2, 0xa4, 0xa5, 0xa6, 0xa8, 0xa9, 0xaa, 0xac, 0xad, 0xae,
+        0xb0, 0xb1, 0xb4, 0xb5, 0xb6, 0xb8, 0xb9, 0xba, 0xbc, 0xbd, 0xbe,
+        0xc0, 0xc1, 0xc4, 0xc5, 0xc6, 0xc8, 0xc9, 0xca, 0xcc, 0xcd, 0xce,
+        0xd0, 0xd1, 0xd5, 0xd6, 0xd8, 0xd9, 0xdd, 0xde,
+        0xe0, 0xe1, 0xe4, 0xe5, 0xe6, 0xe8, 0xe9, 0xea, 0xec, 0xed, 0xee,
+        0xf0, 0xf1, 0xf5, 0xf6, 0xf8, 0xf9, 0xfd, 0xfe
+    ];
+
+    #[test]
+    fn decodes_exactly_the_documented_opcodes() {
+        for opcode in 0..=0xff {
+            let documented = DOCUMENTED.contains(&opcode);
+            assert_eq!(documented, opcode_info(opcode).is_some(), "{:02x}", opcode);
+
+            let cpu = Cpu::new(OpcodeBus(opcode));
+            if !documented {
+                assert!(matches!(cpu.decode_at(0x0000), Err(CpuError::IllegalOpcode { opcode: o, at: 0x0000 }) if o == opcode));
+            }
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use super::*;
+
+const MAGIC : &[u8; 4] = b"ATRN";
+const VERSION : u8 = 3;
+
+/// magic, version, pc, a, x, y, sp, sr, cycles_busy, last_cycles, cycles, the lines, the RIOT
+/// timer, the cartridge bank and the memory size
+const HEADER_SIZE : usize = 4 + 1 + 2 + 7 + 8 + 1 + 7 + 1 + 4;
+
+/// bits of the lines byte
+const READY : u8 = 0x01;
+const IRQ_LINE : u8 = 0x02;
+/// a CLI, SEI or PLP left an I flag for the next interrupt poll, which is in the next bit
+const DELAYED_I : u8 = 0x04;
+const DELAYED_I_SET : u8 = 0x08;
+
+/// stands for "no cartridge" in the bank byte
+const NO_CARTRIDGE : u8 = 0xff;
+
+/// splits the next `len` bytes off `bytes`
+fn take<'a>(bytes : &mut &'a [u8], len : usize) -> Result<&'a [u8], CpuError> {
+    if bytes.len() < len {
+        return Err(CpuError::IncompatibleSaveState("state is truncated"));
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+impl Cpu<Memory> {
+    /// serializes the registers, the interrupt and RDY lines, the RIOT timer, the cartridge bank,
+    /// the whole memory, the RAM poison and the device behind the TIA registers.
+    ///
+    /// layout (little-endian): "ATRN", version, pc, a, x, y, sp, sr, cycles_busy, last_cycles,
+    /// cycles as u64, the lines byte (RDY, IRQ, pending I flag), 1 if there's a RIOT followed by
+    /// its 6 timer bytes, the cartridge bank or 0xff, memory size as u32 and the memory contents.
+    /// then 1 if RAM is poisoned followed by the pattern and one bit per byte nothing wrote to,
+    /// and 1 if there's a TIA followed by the length of its state as u16 and the state
+    pub fn save_state(&self) -> Vec<u8> {
+        let memory = self.memory.bytes();
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + memory.len());
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.pc.to_le_bytes());
+        bytes.extend_from_slice(&[self.a, self.x, self.y, self.sp, self.sr, self.cycles_busy, self.last_cycles]);
+        bytes.extend_from_slice(&self.cycles.to_le_bytes());
+
+        let mut lines = 0;
+        if self.ready {
+            lines |= READY;
+        }
+        if self.irq_line {
+            lines |= IRQ_LINE;
+        }
+        match self.delayed_interrupt_disable {
+            Some(true) => lines |= DELAYED_I | DELAYED_I_SET,
+            Some(false) => lines |= DELAYED_I,
+            None => ()
+        }
+        bytes.push(lines);
+
+        match self.memory.riot() {
+            Some(riot) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&riot.timer_state());
+            },
+            None => bytes.extend_from_slice(&[0; 7])
+        }
+        bytes.push(self.memory.cartridge_bank().unwrap_or(NO_CARTRIDGE));
+
+        bytes.extend_from_slice(&(memory.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(memory);
+
+        match self.memory.poison_state() {
+            Some((value, untouched)) => {
+                bytes.extend_from_slice(&[1, value]);
+                bytes.extend_from_slice(&untouched);
+            },
+            None => bytes.push(0)
+        }
+
+        match self.memory.tia() {
+            Some(tia) => {
+                let state = tia.borrow().save_state();
+                bytes.push(1);
+                bytes.extend_from_slice(&(state.len() as u16).to_le_bytes());
+                bytes.extend_from_slice(&state);
+            },
+            None => bytes.push(0)
+        }
+
+        bytes
+    }
+
+    /// restores a state produced by `save_state`. the memory must have the same size and the
+    /// same chips as the one that was saved. nothing is modified if the state is rejected
+    pub fn load_state(&mut self, bytes : &[u8]) -> Result<(), CpuError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(CpuError::IncompatibleSaveState("header is truncated"));
+        }
+        if &bytes[0..4] != MAGIC {
+            return Err(CpuError::IncompatibleSaveState("not a save state"));
+        }
+        if bytes[4] != VERSION {
+            return Err(CpuError::IncompatibleSaveState("unsupported version"));
+        }
+
+        let lines = bytes[22];
+        if lines & !(READY | IRQ_LINE | DELAYED_I | DELAYED_I_SET) != 0 {
+            return Err(CpuError::IncompatibleSaveState("unknown lines"));
+        }
+
+        let riot = match (bytes[23], self.memory.riot()) {
+            (0, None) => None,
+            (1, Some(riot)) => {
+                let timer : &[u8; 6] = bytes[24..30].try_into().unwrap();
+                match riot.with_timer_state(timer) {
+                    Some(riot) => Some(riot),
+                    None => return Err(CpuError::IncompatibleSaveState("invalid RIOT timer"))
+                }
+            },
+            _ => return Err(CpuError::IncompatibleSaveState("RIOT presence differs"))
+        };
+
+        let bank = bytes[30];
+        match self.memory.cartridge_banks() {
+            None if bank == NO_CARTRIDGE => (),
+            Some(banks) if bank < banks => (),
+            _ => return Err(CpuError::IncompatibleSaveState("cartridge differs"))
+        }
+
+        let memory_size = u32::from_le_bytes([bytes[31], bytes[32], bytes[33], bytes[34]]) as usize;
+        if memory_size != self.memory.bytes().len() {
+            return Err(CpuError::IncompatibleSaveState("memory size differs"));
+        }
+        let mut rest = &bytes[HEADER_SIZE..];
+        let memory = take(&mut rest, memory_size)?;
+
+        let poison = match take(&mut rest, 1)?[0] {
+            0 => None,
+            1 => {
+                let value = take(&mut rest, 1)?[0];
+                Some((value, take(&mut rest, memory_size.div_ceil(8))?))
+            },
+            _ => return Err(CpuError::IncompatibleSaveState("invalid RAM poison"))
+        };
+
+        let tia = match (take(&mut rest, 1)?[0], self.memory.tia()) {
+            (0, None) => None,
+            (1, Some(tia)) => {
+                let len = take(&mut rest, 2)?;
+                let state = take(&mut rest, u16::from_le_bytes([len[0], len[1]]) as usize)?;
+                let mut device = tia.borrow().clone_device();
+                if !device.load_state(state) {
+                    return Err(CpuError::IncompatibleSaveState("invalid TIA state"));
+                }
+                Some(device)
+            },
+            _ => return Err(CpuError::IncompatibleSaveState("TIA presence differs"))
+        };
+
+        if !rest.is_empty() {
+            return Err(CpuError::IncompatibleSaveState("trailing bytes after the state"));
+        }
+
+        self.pc = u16::from_le_bytes([bytes[5], bytes[6]]);
+        self.a = bytes[7];
+        self.x = bytes[8];
+        self.y = bytes[9];
+        self.sp = bytes[10];
+        self.sr = bytes[11];
+        self.cycles_busy = bytes[12];
+        self.last_cycles = bytes[13];
+        self.cycles = u64::from_le_bytes(bytes[14..22].try_into().unwrap());
+        self.ready = lines & READY != 0;
+        self.irq_line = lines & IRQ_LINE != 0;
+        self.delayed_interrupt_disable = (lines & DELAYED_I != 0).then_some(lines & DELAYED_I_SET != 0);
+        if let (Some(riot), Some(saved)) = (self.memory.riot_mut(), riot) {
+            *riot = saved;
+        }
+        if bank != NO_CARTRIDGE {
+            self.memory.set_cartridge_bank(bank);
+        }
+        self.memory.bytes_mut().copy_from_slice(memory);
+        self.memory.restore_poison(poison);
+        if let Some(device) = tia {
+            self.memory.set_tia(device);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn state_round_trips() {
+        let rom = vec![
+            0xa9, 0x42, // LDA #$42
+            0x85, 0x10, // STA $10
+            0x58,       // CLI
+            0xa2, 0x07, // LDX #$07
+            0x38        // SEC
+        ];
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &rom).unwrap();
+        mem.store_word(0xfffe, 0x2000).unwrap();
+        let mut cpu = Cpu::new(mem);
+        cpu.set_flag(CpuFlags::InterruptDisable);
+        cpu.set_irq_line(true);
+
+        for _ in 0..3 {
+            cpu.step().unwrap();
+        }
+        let saved_state = cpu.state();
+        let saved_cycles = cpu.cycles();
+        let saved_last_cycles = cpu.last_instruction_cycles();
+        let saved = cpu.save_state();
+
+        for _ in 0..2 {
+            cpu.step().unwrap();
+        }
+        cpu.set_irq_line(false);
+        cpu.set_rdy(false);
+        cpu.memory.store(0x0010, 0x99).unwrap();
+        cpu.memory.store(0xfffc, 0x01).unwrap();
+        assert_ne!(saved_state, cpu.state());
+
+        cpu.load_state(&saved).unwrap();
+        assert_eq!(saved_state, cpu.state());
+        assert_eq!(saved_cycles, cpu.cycles());
+        assert_eq!(saved_last_cycles, cpu.last_instruction_cycles());
+        assert_eq!(saved, cpu.save_state());
+        assert_eq!(0x42, cpu.memory.load(0x0010).unwrap());
+        assert_eq!(0x00, cpu.memory.load(0xfffc).unwrap());
+
+        // RDY is up again and the IRQ still waits for the instruction after CLI
+        cpu.step().unwrap();
+        assert_eq!(0x1007, cpu.pc);
+        assert_eq!(7, cpu.step().unwrap());
+        assert_eq!(0x2000, cpu.pc);
+    }
+
+    #[test]
+    fn state_round_trips_the_riot_timer_and_the_cartridge_bank() {
+        let mut image = vec![0xaa; 0x1000];
+        image.extend(vec![0xbb; 0x1000]);
+        let mut cpu = Cpu::new(Memory::with_cartridge(&image).unwrap());
+        cpu.memory.store(0x0296, 0x10).unwrap(); // TIM64T
+        cpu.memory.tick(100);
+        cpu.memory.load(0x1ff8).unwrap();
+        let timer = cpu.memory.load(0x0284).unwrap(); // INTIM
+        let saved = cpu.save_state();
+
+        cpu.memory.load(0x1ff9).unwrap();
+        cpu.memory.store(0x0297, 0x80).unwrap(); // T1024T
+        assert_eq!(0xbb, cpu.memory.load(0x1000).unwrap());
+
+        cpu.load_state(&saved).unwrap();
+        assert_eq!(saved, cpu.save_state());
+        assert_eq!(0xaa, cpu.memory.load(0x1000).unwrap());
+        assert_eq!(timer, cpu.memory.load(0x0284).unwrap());
+        // 28 cycles left of the prescaler before the next decrement
+        cpu.memory.tick(27);
+        assert_eq!(timer, cpu.memory.load(0x0284).unwrap());
+        cpu.memory.tick(1);
+        assert_eq!(timer - 1, cpu.memory.load(0x0284).unwrap());
+    }
+
+    /// the steps RDY stays low for before the next instruction runs
+    fn steps_until_ready(cpu : &mut Cpu) -> usize {
+        let mut halted = 0;
+        while cpu.step().unwrap() == 1 {
+            halted += 1;
+        }
+        halted
+    }
+
+    #[test]
+    fn state_round_trips_the_tia_mid_wsync() {
+        let mut image = vec![0xea; 0x1000];
+        image[0x000..0x004].copy_from_slice(&[
+            0x85, 0x09, // STA COLUBK
+            0x85, 0x02  // STA WSYNC
+        ]);
+        image[0xffc..0xffe].copy_from_slice(&[0x00, 0xf0]);
+        let mut mem = Memory::with_cartridge(&image).unwrap();
+        mem.set_6507_addressing(true);
+        let mut cpu = Cpu::new_from_reset(mem).unwrap();
+        cpu.a = 0x2c;
+
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert!(!cpu.is_ready());
+        let saved = cpu.save_state();
+
+        let halted = steps_until_ready(&mut cpu);
+        assert!(halted > 0);
+        cpu.memory.store(0x0009, 0x00).unwrap();
+
+        cpu.load_state(&saved).unwrap();
+        assert!(!cpu.is_ready());
+        assert_eq!(0x2c, cpu.memory.load(0x0009).unwrap());
+        assert_eq!(saved, cpu.save_state());
+        assert_eq!(halted, steps_until_ready(&mut cpu));
+    }
+
+    #[test]
+    fn state_round_trips_the_ram_poison() {
+        let mut cpu = Cpu::new(Memory::new_atari());
+        cpu.memory.set_ram_poison(Some(0xa5));
+        cpu.memory.clear_ram();
+        cpu.memory.store(0x0080, 0x01).unwrap();
+        let saved = cpu.save_state();
+
+        let reads = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&reads);
+        cpu.memory.set_uninitialized_read_hook(Box::new(move |addr| sink.borrow_mut().push(addr)));
+        cpu.memory.store(0x0081, 0x02).unwrap();
+        cpu.memory.set_ram_poison(None);
+
+        cpu.load_state(&saved).unwrap();
+        assert_eq!(saved, cpu.save_state());
+        assert_eq!(0xa5, cpu.memory.load(0x0081).unwrap());
+        cpu.memory.load(0x0080).unwrap();
+        assert_eq!(vec![0x0081], *reads.borrow());
+    }
+
+    #[test]
+    fn rejects_incompatible_states() {
+        let mut cpu = Cpu::new(Memory::new(64*1024).unwrap());
+        let saved = cpu.save_state();
+
+        let mut wrong_magic = saved.clone();
+        wrong_magic[0] = b'X';
+        assert!(matches!(cpu.load_state(&wrong_magic), Err(CpuError::IncompatibleSaveState(_))));
+
+        let mut wrong_version = saved.clone();
+        wrong_version[4] = VERSION + 1;
+        assert!(matches!(cpu.load_state(&wrong_version), Err(CpuError::IncompatibleSaveState(_))));
+
+        assert!(matches!(cpu.load_state(&saved[..saved.len() - 1]), Err(CpuError::IncompatibleSaveState(_))));
+        assert!(matches!(cpu.load_state(&saved[..3]), Err(CpuError::IncompatibleSaveState(_))));
+
+        let smaller = Cpu::new(Memory::new(16*1024).unwrap()).save_state();
+        assert!(matches!(cpu.load_state(&smaller), Err(CpuError::IncompatibleSaveState(_))));
+
+        // a 2600 with a cartridge doesn't load into one without
+        let mut atari = Cpu::new(Memory::with_cartridge(&[0xea; 0x1000]).unwrap());
+        let with_cartridge = atari.save_state();
+        let mut without_cartridge = Cpu::new(Memory::new_atari());
+        assert!(matches!(without_cartridge.load_state(&with_cartridge), Err(CpuError::IncompatibleSaveState(_))));
+        assert!(matches!(atari.load_state(&without_cartridge.save_state()), Err(CpuError::IncompatibleSaveState(_))));
+
+        let mut broken_timer = with_cartridge.clone();
+        broken_timer[26] = 0x00; // interval 0
+        assert!(matches!(atari.load_state(&broken_timer), Err(CpuError::IncompatibleSaveState(_))));
+        let mut broken_tia = with_cartridge.clone();
+        let beam = broken_tia.len() - 2;
+        broken_tia[beam] = 0xff;
+        assert!(matches!(atari.load_state(&broken_tia), Err(CpuError::IncompatibleSaveState(_))));
+        assert_eq!(with_cartridge, atari.save_state());
+    }
+}
@@ -1,3 +1,6 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use super::*;
 
 #[test]
@@ -5,12 +8,14 @@ fn initializes() {
     let mem = Memory::new(16*1024).unwrap();
 
     let cpu = Cpu::new(mem);
-    assert_eq!(cpu.sp, 0xff);
-    assert_eq!(cpu.pc, 0x1000);
-    assert_eq!(cpu.a, 0x0);
-    assert_eq!(cpu.x, 0x0);
-    assert_eq!(cpu.y, 0x0);
-    assert_eq!(cpu.sr, 0x0);
+    assert_eq!(CpuState {
+        pc: 0x1000,
+        a: 0x0,
+        x: 0x0,
+        y: 0x0,
+        sp: 0xff,
+        sr: 0x20 // the unused bit reads as set
+    }, cpu.state());
     assert_eq!(cpu.memory.load(0x0000).unwrap(), 0);
     assert_eq!(cpu.cycles_busy, 0);
 }
@@ -19,8 +24,8 @@ fn initializes() {
 fn loads_little_endian_word () {
     let mut mem = Memory::new(64*1024).unwrap();
 
-    mem.load_rom(0x0000, &vec![0xab, 0xcd]);
-    mem.load_rom(0xfffe, &vec![0xdc, 0xba]);
+    mem.load_rom(0x0000, &[0xab, 0xcd]).unwrap();
+    mem.load_rom(0xfffe, &[0xdc, 0xba]).unwrap();
 
     let cpu = Cpu::new(mem);
     assert_eq!(0xcdab, cpu.load_little_endian_u16(0x0000).unwrap());
@@ -28,31 +33,106 @@ fn loads_little_endian_word () {
 }
 
 #[test]
-#[should_panic]
-fn panics_on_invalid_word_read () {
-    let mem = Memory::new(64*1024).unwrap();
+fn word_reads_wrap_at_the_top_of_memory () {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0xffff, &[0x34]).unwrap();
+    mem.load_rom(0x0000, &[0x12]).unwrap();
     let cpu = Cpu::new(mem);
-    _ = cpu.load_little_endian_u16(0xffff);
+    assert_eq!(0x1234, cpu.load_little_endian_u16(0xffff).unwrap());
+
+    // in 16K the low byte is already missing
+    let cpu = Cpu::new(Memory::new(16*1024).unwrap());
+    assert_eq!(0xffff, cpu.load_little_endian_u16(0xffff).unwrap_err().value());
 }
 
+#[test]
+fn word_stores_and_reads_agree_at_the_top_of_memory() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.store_word(0xffff, 0xabcd).unwrap();
+    let cpu = Cpu::new(mem);
+
+    assert_eq!(0xabcd, cpu.load_little_endian_u16(0xffff).unwrap());
+    assert_eq!(0xab, cpu.memory.load(0x0000).unwrap());
+}
+
+#[test]
+fn word_reads_report_the_byte_past_the_end() {
+    let mem = Memory::new(16*1024).unwrap();
+    let cpu = Cpu::new(mem);
+
+    assert!(cpu.load_little_endian_u16(0x3ffe).is_ok());
+    // the low byte is there, the high one isn't
+    assert_eq!(0x4000, cpu.load_little_endian_u16(0x3fff).unwrap_err().value());
+    assert_eq!(0x4000, cpu.load_little_endian_u16(0x4000).unwrap_err().value());
+
+    // no reset vector in 16K
+    match Cpu::new_from_reset(Memory::new(16*1024).unwrap()) {
+        Err(CpuError::MemoryBoundsError(e)) => assert_eq!(0xfffc, e.value()),
+        _ => panic!("reset should fail without a reset vector")
+    }
+}
 
 #[test]
 fn resets_properly() {
     let mut mem = Memory::new(65536).unwrap();
 
-    mem.load_rom(0xfffc, &vec![0x02, 0x03]);
+    mem.load_rom(0xfffc, &[0x02, 0x03]).unwrap();
     let mut cpu = Cpu::new(mem);
-    cpu.reset();
-    assert_eq!(cpu.sp, 0xff);
-    assert_eq!(cpu.pc, 0x0302);
-    assert_eq!(cpu.a, 0x0);
-    assert_eq!(cpu.x, 0x0);
-    assert_eq!(cpu.y, 0x0);
-    assert_eq!(cpu.sr, 0x0);
+    cpu.a = 0x12;
+    cpu.reset().unwrap();
+    assert_eq!(CpuState {
+        pc: 0x0302,
+        a: 0x12,
+        x: 0x0,
+        y: 0x0,
+        sp: 0xfd,
+        sr: 0x24 // interrupts disabled, and the unused bit reads as set
+    }, cpu.state());
     assert_eq!(cpu.load_little_endian_u16(0xfffc).unwrap(), 0x0302);
     assert_eq!(cpu.cycles_busy, 1);
 }
 
+#[test]
+fn new_from_reset_starts_at_the_reset_vector() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.store_word(0xfffc, 0xf000).unwrap();
+
+    let cpu = Cpu::new_from_reset(mem).unwrap();
+    assert_eq!(0xf000, cpu.pc());
+    assert!(cpu.flag(CpuFlags::InterruptDisable));
+
+    let mem = Memory::new(16*1024).unwrap();
+    assert!(Cpu::new_from_reset(mem).is_err());
+}
+
+#[test]
+fn ram_survives_reset_but_not_power_on() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0xfffc, &[0x00, 0xf0]).unwrap();
+    mem.load_rom(0xf000, &[0xea]).unwrap();
+    mem.set_rom_region(0xf000, 0xffff);
+    mem.store(0x0080, 0x42).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.x = 0x10;
+
+    cpu.reset().unwrap();
+    assert_eq!(0x42, cpu.memory.load(0x0080).unwrap());
+    assert_eq!(0x10, cpu.x);
+    assert!(cpu.get_flag(CpuFlags::InterruptDisable));
+
+    cpu.power_on().unwrap();
+    assert_eq!(0x00, cpu.memory.load(0x0080).unwrap());
+    assert_eq!(0xea, cpu.memory.load(0xf000).unwrap());
+    assert_eq!(CpuState {
+        pc: 0xf000,
+        a: 0x0,
+        x: 0x0,
+        y: 0x0,
+        sp: 0xfd,
+        sr: 0x24
+    }, cpu.state());
+}
+
 #[test]
 fn can_fetch_brk_ora_instructions() {
     let mut mem = Memory::new(16*1024).unwrap();
@@ -67,7 +147,7 @@ fn can_fetch_brk_ora_instructions() {
         0x01, 0x03,
         0x11, 0x03
     ];
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
 
     let mut cpu = Cpu::new(mem);
 
@@ -129,7 +209,7 @@ fn can_fetch_asl_instructions() {
         0x0e, 0x10, 0x45,
         0x1e, 0x11, 0x45
     ];
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
 
     let mut cpu = Cpu::new(mem);
     cpu.x = 0x0a;
@@ -169,7 +249,7 @@ fn can_fetch_php_instruction () {
         0x0a
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -192,7 +272,7 @@ fn can_fetch_bpl_instruction () {
         0x0a
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -214,7 +294,7 @@ fn can_fetch_clc_instruction () {
         0x18
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -231,7 +311,7 @@ fn can_fetch_jsr_instruction () {
         0x20, 0xff, 0x10
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -255,7 +335,7 @@ fn can_fetch_and_instructions () {
         0x31, 0x30
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
     cpu.x = 0xa1;
     cpu.y = 0xa2;
@@ -310,7 +390,7 @@ fn can_fetch_bit_instructions() {
         0x2c, 0x98, 0x99
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -336,7 +416,7 @@ fn can_fetch_rol_instructions() {
         0x3e, 0xcc, 0xaa
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x15;
@@ -377,7 +457,7 @@ fn can_fetch_bmi_sec_rti_instructions() {
         0x40
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -412,7 +492,7 @@ fn can_fetch_eor_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x05;
@@ -472,7 +552,7 @@ fn can_fetch_lsr_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x02;
@@ -516,7 +596,7 @@ fn can_fetch_jmp_bvc_cli_rts_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -560,7 +640,7 @@ fn can_fetch_adc_instructions() {
         0x71, 0x80
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x01;
@@ -619,7 +699,7 @@ fn can_fetch_ror_instructions() {
         0x7e, 0x21, 0x0a
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x10;
@@ -667,7 +747,7 @@ fn can_fetch_bvs_sei_sta_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x5;
@@ -732,7 +812,7 @@ fn can_fetch_stx_sty_instructions() {
         0x8c, 0x06, 0x01
     ];
     let mut mem = Memory::new(64 * 1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x8;
@@ -781,7 +861,7 @@ fn can_fetch_dey_txa_bcc_tya_txs_instructions() {
         0x9a
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x0a;
@@ -824,7 +904,7 @@ fn can_fetch_ldy_instructions() {
         0xbc, 0x03, 0x30
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x01;
@@ -870,7 +950,7 @@ fn can_fetch_lda_instructions() {
         0xb1, 0x33
     ];
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 5;
@@ -930,7 +1010,7 @@ fn can_fetch_tay_tax_bcs_clv_tsx_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -970,7 +1050,7 @@ fn can_fetch_cpy_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -1005,7 +1085,7 @@ fn can_fetch_cmp_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x08;
@@ -1064,7 +1144,7 @@ fn can_fetch_dec_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x15;
@@ -1105,7 +1185,7 @@ fn can_fetch_iny_dex_bne_cld_cpx_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -1160,7 +1240,7 @@ fn can_fetch_sbc_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0xa1;
@@ -1219,7 +1299,7 @@ fn can_fetch_inc_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.x = 0x05;
@@ -1256,7 +1336,7 @@ fn can_fetch_inx_beq_sed_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -1288,7 +1368,7 @@ fn can_fetch_ldx_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     cpu.y = 0x0b;
@@ -1330,7 +1410,7 @@ fn can_fetch_pha_pla_plp_instructions() {
     ];
 
     let mut mem = Memory::new(64*1024).unwrap();
-    mem.load_rom(0x1000, &rom);
+    mem.load_rom(0x1000, &rom).unwrap();
     let mut cpu = Cpu::new(mem);
 
     assert_eq!(Instruction {
@@ -1351,4 +1431,1074 @@ fn can_fetch_pha_pla_plp_instructions() {
     assert_eq!(cpu.pc, 0x1000 + rom.len() as u16);
 }
 
-// TODO: test get_effective_address, get_operand
\ No newline at end of file
+// TODO: test get_effective_address, get_operand
+#[test]
+fn decode_at_does_not_move_pc() {
+    let rom = vec![
+        0xa9, 0xba,
+        0x8d, 0x05, 0x00
+    ];
+
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    let (decoded, size) = cpu.decode_at(0x1002).unwrap();
+    assert_eq!(cpu.pc, 0x1000);
+    assert_eq!(size, 3);
+    assert_eq!(Instruction {
+        operation: Operations::StoreAccumulator,
+        addressing: Addressing::Absolute(0x0005),
+        cycle_count: 4
+    }, decoded);
+
+    let (decoded, size) = cpu.decode_at(cpu.pc).unwrap();
+    assert_eq!(cpu.pc, 0x1000);
+    assert_eq!(size, 2);
+    assert_eq!(cpu.fetch().unwrap(), decoded);
+    assert_eq!(cpu.pc, 0x1000 + size);
+}
+
+#[test]
+fn trace_sees_every_executed_instruction() {
+    let rom = vec![
+        0xa9, 0x01,
+        0x85, 0x10,
+        0xea
+    ];
+
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let log = Rc::clone(&seen);
+    cpu.set_trace(Box::new(move |pc, instruction, state| {
+        log.borrow_mut().push((pc, instruction.operation.to_string(), state.a));
+    }));
+
+    for _ in 0..3 {
+        cpu.step().unwrap();
+    }
+
+    assert_eq!(*seen.borrow(), vec![
+        (0x1000, String::from("LDA"), 0x00),
+        (0x1002, String::from("STA"), 0x01),
+        (0x1004, String::from("NOP"), 0x01),
+    ]);
+    assert_eq!(cpu.memory.load(0x0010).unwrap(), 0x01);
+}
+
+#[test]
+fn clock_sink_gets_the_cycles_of_each_instruction() {
+    let rom = vec![
+        0xa9, 0x01,       // LDA #$01
+        0x8d, 0x00, 0x20, // STA $2000
+        0xbd, 0xff, 0x20, // LDA $20ff,X crossing a page
+        0xea
+    ];
+
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.x = 1;
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let log = Rc::clone(&seen);
+    cpu.set_clock_sink(Box::new(move |cycles| log.borrow_mut().push(cycles)));
+
+    let mut total = 0;
+    for _ in 0..4 {
+        total += cpu.step().unwrap() as u64;
+    }
+
+    assert_eq!(*seen.borrow(), vec![2, 4, 5, 2]);
+    assert_eq!(total, cpu.cycles());
+}
+
+#[test]
+fn rdy_low_halts_the_cpu() {
+    let rom = vec![
+        0xa9, 0x01, // LDA #$01
+        0xa9, 0x02  // LDA #$02
+    ];
+
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    cpu.step().unwrap();
+    cpu.set_rdy(false);
+    for _ in 0..3 {
+        assert_eq!(1, cpu.step().unwrap());
+    }
+    assert_eq!(0x1002, cpu.pc);
+    assert_eq!(0x01, cpu.a);
+    assert_eq!(5, cpu.cycles());
+
+    cpu.set_rdy(true);
+    assert_eq!(2, cpu.step().unwrap());
+    assert_eq!(0x1004, cpu.pc);
+    assert_eq!(0x02, cpu.a);
+}
+
+#[test]
+fn zeropage_addresses_wrap_at_ff() {
+    assert_eq!(0x00ff, Cpu::<Memory>::zp_addr(0xff, 0x00));
+    assert_eq!(0x0000, Cpu::<Memory>::zp_addr(0xff, 0x01));
+    assert_eq!(0x007f, Cpu::<Memory>::zp_addr(0x80, 0xff));
+}
+
+#[test]
+fn zeropage_indexing_and_pointers_stay_in_the_zeropage() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x0000, &[0x34, 0x12]).unwrap();
+    mem.load_rom(0x00ff, &[0x00]).unwrap();
+    mem.load_rom(0x0100, &[0x99]).unwrap(); // would be the high byte without the wrap
+    let cpu = Cpu::new(mem);
+
+    assert_eq!(0x0010, cpu.get_effective_address(Addressing::IndexedZeropage(0xf0, 0x20)).unwrap());
+    // pointer at 0xff + 0x01 = 0x00
+    assert_eq!(0x1234, cpu.get_effective_address(Addressing::PreindexedIndirect(0xff, 0x01)).unwrap());
+    // pointer at 0xff, high byte from 0x00
+    assert_eq!(0x3402, cpu.get_effective_address(Addressing::PostindexedIndirect(0xff, 0x02)).unwrap());
+}
+
+#[test]
+fn branches_pay_for_being_taken_and_crossing_a_page() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &[0xd0, 0x10]).unwrap(); // BNE +$10
+    mem.load_rom(0x10f0, &[0xf0, 0x10]).unwrap(); // BEQ +$10, into the next page
+    let mut cpu = Cpu::new(mem);
+
+    cpu.set_flag(CpuFlags::Zero);
+    assert_eq!(2, cpu.step().unwrap());
+    assert_eq!(2, cpu.last_instruction_cycles());
+    assert_eq!(0x1002, cpu.pc);
+
+    cpu.pc = 0x1000;
+    cpu.clear_flag(CpuFlags::Zero);
+    assert_eq!(3, cpu.step().unwrap());
+    assert_eq!(3, cpu.last_instruction_cycles());
+    assert_eq!(0x1012, cpu.pc);
+
+    cpu.pc = 0x10f0;
+    cpu.set_flag(CpuFlags::Zero);
+    assert_eq!(4, cpu.step().unwrap());
+    assert_eq!(4, cpu.last_instruction_cycles());
+    assert_eq!(0x1102, cpu.pc);
+}
+
+#[test]
+fn relative_targets_are_signed() {
+    assert_eq!(0x1002, relative_target(0x1002, 0x00));
+    assert_eq!(0x1081, relative_target(0x1002, 0x7f));
+    assert_eq!(0x0f82, relative_target(0x1002, 0x80));
+    assert_eq!(0x1001, relative_target(0x1002, 0xff));
+    assert_eq!(0x0001, relative_target(0xfffe, 0x03));
+}
+
+#[test]
+fn branches_go_backwards() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &[0x10, 0xfe]).unwrap(); // BPL to itself
+    let mut cpu = Cpu::new(mem);
+
+    assert_eq!(3, cpu.step().unwrap());
+    assert_eq!(0x1000, cpu.pc);
+}
+
+#[test]
+fn fetches_from_a_pc_that_was_set() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x0400, &[0xa9, 0x42]).unwrap();
+    mem.load_rom(0x0600, &[0xea]).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    cpu.set_pc(0x0400);
+    assert_eq!(0x0400, cpu.pc());
+    assert_eq!(Instruction {
+        operation: Operations::LoadAccumulator,
+        addressing: Addressing::Immediate(0x42),
+        cycle_count: 2
+    }, cpu.fetch().unwrap());
+
+    cpu.jump_to(0x0600);
+    assert_eq!(Operations::NoOperation, cpu.fetch().unwrap().operation);
+    assert_eq!(0x0601, cpu.pc());
+}
+
+#[test]
+fn indexed_opcodes_use_the_right_register() {
+    let zeropage_x = [0x15, 0x16, 0x35, 0x36, 0x55, 0x56, 0x75, 0x76, 0x94, 0x95, 0xb4, 0xb5, 0xd5, 0xd6, 0xf5, 0xf6];
+    let zeropage_y = [0x96, 0xb6];
+    let absolute_x = [0x1d, 0x1e, 0x3d, 0x3e, 0x5d, 0x5e, 0x7d, 0x7e, 0x9d, 0xbc, 0xbd, 0xdd, 0xde, 0xfd, 0xfe];
+    let absolute_y = [0x19, 0x39, 0x59, 0x79, 0x99, 0xb9, 0xbe, 0xd9, 0xf9];
+
+    let mut cpu = Cpu::new(Memory::new(64*1024).unwrap());
+    cpu.x = 0x11;
+    cpu.y = 0x22;
+
+    let mut check = |opcode : u8, expected : Addressing| {
+        cpu.memory.load_rom(0x1000, &[opcode, 0x34, 0x12]).unwrap();
+        let (instruction, _) = cpu.decode_at(0x1000).unwrap();
+        assert_eq!(expected, instruction.addressing, "opcode {:02x}", opcode);
+    };
+
+    for opcode in zeropage_x {
+        check(opcode, Addressing::IndexedZeropage(0x34, 0x11));
+    }
+    for opcode in zeropage_y {
+        check(opcode, Addressing::IndexedZeropage(0x34, 0x22));
+    }
+    for opcode in absolute_x {
+        check(opcode, Addressing::IndexedAbsolute(0x1234, 0x11));
+    }
+    for opcode in absolute_y {
+        check(opcode, Addressing::IndexedAbsolute(0x1234, 0x22));
+    }
+}
+
+#[test]
+fn indexed_read_modify_write_never_pays_for_crossing_a_page() {
+    let rom = vec![
+        0xfe, 0x00, 0x20, // INC $2000,X
+        0xfe, 0xff, 0x20, // INC $20ff,X crossing into 0x2100
+        0x1e, 0xff, 0x20  // ASL $20ff,X
+    ];
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.x = 0x01;
+
+    assert_eq!(7, cpu.step().unwrap());
+    assert_eq!(7, cpu.step().unwrap());
+    assert_eq!(7, cpu.step().unwrap());
+    assert_eq!(0x01, cpu.memory.load(0x2001).unwrap());
+    assert_eq!(0x02, cpu.memory.load(0x2100).unwrap());
+}
+
+#[test]
+fn inc_and_dec_wrap_around() {
+    let rom = vec![
+        0xe6, 0x10,       // INC $10
+        0xee, 0x00, 0x20, // INC $2000
+        0xc6, 0x11,       // DEC $11
+        0xce, 0x01, 0x20  // DEC $2001
+    ];
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    mem.load_rom(0x0010, &[0xff, 0x00]).unwrap();
+    mem.load_rom(0x2000, &[0xff, 0x00]).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    for addr in [0x0010, 0x2000] {
+        cpu.step().unwrap();
+        assert_eq!(0x00, cpu.memory.load(addr).unwrap());
+        assert!(cpu.get_flag(CpuFlags::Zero));
+        assert!(!cpu.get_flag(CpuFlags::Negative));
+    }
+
+    for addr in [0x0011, 0x2001] {
+        cpu.step().unwrap();
+        assert_eq!(0xff, cpu.memory.load(addr).unwrap());
+        assert!(!cpu.get_flag(CpuFlags::Zero));
+        assert!(cpu.get_flag(CpuFlags::Negative));
+    }
+    assert_eq!(5 + 6 + 5 + 6, cpu.cycles());
+}
+
+#[test]
+fn ticks_run_instructions_as_their_cycles_pass() {
+    let rom = vec![
+        0xa9, 0x01,       // LDA #$01, 2 cycles
+        0xee, 0x00, 0x20, // INC $2000, 6 cycles
+        0xa9, 0x02        // LDA #$02, 2 cycles
+    ];
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    cpu.tick().unwrap();
+    assert_eq!(0x1002, cpu.pc);
+    assert_eq!(1, cpu.busy_cycles());
+
+    for _ in 1..8 {
+        cpu.tick().unwrap();
+    }
+    assert_eq!(0x1005, cpu.pc);
+    assert_eq!(0, cpu.busy_cycles());
+    assert_eq!(0x01, cpu.memory.load(0x2000).unwrap());
+    assert_eq!(0x01, cpu.a);
+    assert_eq!(8, cpu.cycles());
+
+    cpu.tick().unwrap();
+    assert_eq!(0x02, cpu.a);
+}
+
+#[test]
+fn truncated_instruction_is_reported() {
+    let mut mem = Memory::with_capacity(0x100).unwrap();
+    mem.load_rom(0x00ff, &[0x4c]).unwrap(); // JMP absolute, with no room for the address
+    let mut cpu = Cpu::new(mem);
+    cpu.pc = 0x00ff;
+
+    assert!(matches!(cpu.fetch(), Err(CpuError::IncompleteInstruction { opcode: 0x4c, at: 0x00ff })));
+    assert_eq!(0x00ff, cpu.pc);
+    cpu.pc = 0x0100;
+    assert!(matches!(cpu.fetch(), Err(CpuError::MemoryBoundsError(_))));
+}
+
+#[test]
+fn pla_sets_flags_but_pha_does_not() {
+    let rom = vec![
+        0x48, // PHA
+        0xa9, 0x80, // LDA #$80
+        0x48, // PHA
+        0x68, // PLA
+        0x68  // PLA
+    ];
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.set_flag(CpuFlags::Carry);
+
+    cpu.step().unwrap();
+    assert_eq!(0x00, cpu.memory.load(0x01ff).unwrap());
+    assert_eq!(0xfe, cpu.sp);
+    assert_eq!(0b0010_0001, cpu.sr());
+
+    cpu.step().unwrap();
+    let flags = cpu.sr();
+    cpu.step().unwrap();
+    assert_eq!(flags, cpu.sr());
+    assert_eq!(0x80, cpu.memory.load(0x01fe).unwrap());
+
+    cpu.a = 0x00;
+    cpu.step().unwrap();
+    assert_eq!(0x80, cpu.a);
+    assert!(cpu.get_flag(CpuFlags::Negative));
+    assert!(!cpu.get_flag(CpuFlags::Zero));
+
+    cpu.step().unwrap();
+    assert_eq!(0x00, cpu.a);
+    assert!(cpu.get_flag(CpuFlags::Zero));
+    assert!(!cpu.get_flag(CpuFlags::Negative));
+    assert_eq!(0xff, cpu.sp);
+    assert_eq!(3 + 2 + 3 + 4 + 4, cpu.cycles());
+}
+
+#[test]
+fn jmp_indirect_page_bug_is_nmos_only() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &[0x6c, 0xff, 0x20]).unwrap(); // JMP ($20ff)
+    mem.load_rom(0x20ff, &[0x34, 0x12]).unwrap();
+    mem.load_rom(0x2000, &[0x56]).unwrap();
+
+    let mut cpu = Cpu::new(mem);
+    assert_eq!(CpuVariant::Nmos, cpu.variant());
+    cpu.step().unwrap();
+    assert_eq!(0x5634, cpu.pc);
+
+    cpu.pc = 0x1000;
+    cpu.set_variant(CpuVariant::Cmos);
+    cpu.step().unwrap();
+    assert_eq!(0x1234, cpu.pc);
+}
+
+#[test]
+fn nop_only_moves_pc() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &[0xea]).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.a = 0x12;
+    cpu.x = 0x34;
+    cpu.sr = 0b10000001;
+
+    let before = cpu.state();
+    assert_eq!(2, cpu.step().unwrap());
+    assert_eq!(CpuState { pc: before.pc + 1, ..before }, cpu.state());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn cpu_round_trips_through_json() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &[0xa9, 0x42, 0x85, 0x10, 0xa2, 0x07]).unwrap();
+    let mut cpu = Cpu::new(mem);
+    for _ in 0..3 {
+        cpu.step().unwrap();
+    }
+
+    let json = serde_json::to_string(&cpu).unwrap();
+    let restored : Cpu = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(cpu.state(), restored.state());
+    assert_eq!(cpu.save_state(), restored.save_state());
+
+    let state_json = serde_json::to_string(&cpu.state()).unwrap();
+    assert_eq!(cpu.state(), serde_json::from_str::<CpuState>(&state_json).unwrap());
+}
+
+/// 64K of memory that remembers every access, in order
+struct RecordingBus {
+    data: Vec<u8>,
+    accesses: RefCell<Vec<(&'static str, u16)>>
+}
+
+impl Bus for RecordingBus {
+    fn load(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+        self.accesses.borrow_mut().push(("load", addr));
+        Ok(self.data[addr as usize])
+    }
+
+    fn store(&mut self, addr : u16, v : u8) -> Result<(), OutOfRangeError> {
+        self.accesses.borrow_mut().push(("store", addr));
+        self.data[addr as usize] = v;
+        Ok(())
+    }
+
+    /// peeks aren't bus accesses
+    fn peek(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+        Ok(self.data[addr as usize])
+    }
+}
+
+#[test]
+fn cpu_goes_through_the_bus() {
+    let mut data = vec![0x00; 64*1024];
+    data[0x1000..0x1004].copy_from_slice(&[
+        0xa5, 0x20,
+        0x85, 0x21
+    ]);
+    data[0x0020] = 0x5a;
+    let bus = RecordingBus { data, accesses: RefCell::new(Vec::new()) };
+
+    let mut cpu = Cpu::new(bus);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.a, 0x5a);
+    assert_eq!(cpu.memory.data[0x0021], 0x5a);
+    assert_eq!(*cpu.memory.accesses.borrow(), vec![
+        ("load", 0x1000), ("load", 0x1001), ("load", 0x0020),
+        ("load", 0x1002), ("load", 0x1003), ("store", 0x0021),
+    ]);
+}
+
+/// 64K of memory that remembers every store and what it wrote
+struct StoreLogBus {
+    data: Vec<u8>,
+    stores: Vec<(u16, u8)>
+}
+
+impl Bus for StoreLogBus {
+    fn load(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+        Ok(self.data[addr as usize])
+    }
+
+    fn store(&mut self, addr : u16, v : u8) -> Result<(), OutOfRangeError> {
+        self.stores.push((addr, v));
+        self.data[addr as usize] = v;
+        Ok(())
+    }
+}
+
+#[test]
+fn read_modify_write_stores_the_old_value_first() {
+    let mut data = vec![0x00; 64*1024];
+    data[0x1000..0x1004].copy_from_slice(&[
+        0xe6, 0x20, // INC $20
+        0x06, 0x21  // ASL $21
+    ]);
+    data[0x0020] = 0x41;
+    data[0x0021] = 0x81;
+    let bus = StoreLogBus { data, stores: Vec::new() };
+
+    let mut cpu = Cpu::new(bus);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.memory.stores, vec![
+        (0x0020, 0x41), (0x0020, 0x42),
+        (0x0021, 0x81), (0x0021, 0x02),
+    ]);
+    assert!(cpu.get_flag(CpuFlags::Carry));
+}
+
+#[test]
+fn shifts_and_rotates() {
+    let rom = vec![
+        0x0a,       // ASL A
+        0x2a,       // ROL A
+        0x4a,       // LSR A
+        0x6a,       // ROR A
+        0x66, 0x10, // ROR $10
+        0x4e, 0x11, 0x00 // LSR $0011
+    ];
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    mem.load_rom(0x0010, &[0x02, 0x01]).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.a = 0b1100_0001;
+
+    cpu.step().unwrap();
+    assert_eq!(0b1000_0010, cpu.a);
+    assert!(cpu.get_flag(CpuFlags::Carry));
+    assert!(cpu.get_flag(CpuFlags::Negative));
+
+    cpu.step().unwrap();
+    assert_eq!(0b0000_0101, cpu.a);
+    assert!(cpu.get_flag(CpuFlags::Carry));
+    assert!(!cpu.get_flag(CpuFlags::Negative));
+
+    cpu.step().unwrap();
+    assert_eq!(0b0000_0010, cpu.a);
+    assert!(cpu.get_flag(CpuFlags::Carry));
+
+    cpu.step().unwrap();
+    assert_eq!(0b1000_0001, cpu.a);
+    assert!(!cpu.get_flag(CpuFlags::Carry));
+    assert!(cpu.get_flag(CpuFlags::Negative));
+
+    cpu.step().unwrap();
+    assert_eq!(0x01, cpu.memory.load(0x0010).unwrap());
+    assert!(!cpu.get_flag(CpuFlags::Carry));
+
+    cpu.step().unwrap();
+    assert_eq!(0x00, cpu.memory.load(0x0011).unwrap());
+    assert!(cpu.get_flag(CpuFlags::Carry));
+    assert!(cpu.get_flag(CpuFlags::Zero));
+}
+
+#[test]
+fn stores_reach_the_tia() {
+    let rom = vec![
+        0xa9, 0x9a, // LDA #$9a
+        0x85, 0x09, // STA COLUBK
+        0xa6, 0x49  // LDX COLUBK, through a mirror
+    ];
+    let mut mem = Memory::new_atari();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    for _ in 0..3 {
+        cpu.step().unwrap();
+    }
+    assert_eq!(0x9a, cpu.x);
+}
+
+#[test]
+fn wsync_halts_until_the_next_scanline() {
+    let mut rom = vec![0xea; 5]; // 10 cycles of NOPs
+    rom.extend(vec![
+        0x85, 0x02, // STA WSYNC
+        0xa9, 0x01  // LDA #$01
+    ]);
+    let mut mem = Memory::new_atari();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    for _ in 0..6 {
+        cpu.step().unwrap();
+    }
+    assert_eq!(13, cpu.cycles());
+
+    for _ in 13..76 {
+        assert_eq!(1, cpu.step().unwrap());
+        assert_eq!(0x1007, cpu.pc);
+    }
+    assert_eq!(76, cpu.cycles());
+
+    assert_eq!(2, cpu.step().unwrap());
+    assert_eq!(0x01, cpu.a);
+}
+
+#[test]
+fn step_drives_the_riot_timer() {
+    let mut rom = vec![
+        0xa9, 0x02,
+        0x8d, 0x97, 0x02 // STA T1024T
+    ];
+    rom.extend(vec![0xea; 512]);
+    rom.extend(vec![
+        0xad, 0x84, 0x02 // LDA INTIM
+    ]);
+
+    let mut mem = Memory::new_atari();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    cpu.step().unwrap();
+    assert_eq!(4, cpu.step().unwrap());
+    for _ in 0..509 {
+        cpu.step().unwrap();
+    }
+    // the 4 cycles of the STA count too, so 1022 cycles went by
+    assert_eq!(0x02, cpu.memory.load(0x0284).unwrap());
+    cpu.step().unwrap();
+    assert_eq!(0x01, cpu.memory.load(0x0284).unwrap());
+
+    for _ in 0..3 {
+        cpu.step().unwrap();
+    }
+    assert_eq!(0x01, cpu.a);
+}
+
+#[test]
+fn indexed_reads_pay_for_crossing_a_page() {
+    let rom = vec![
+        0xbd, 0x10, 0x20, // LDA $2010,X stays in page 0x20
+        0xbd, 0xf0, 0x20, // LDA $20f0,X lands on 0x2110
+        0xb1, 0x40,       // LDA ($40),Y crosses too
+        0x9d, 0xf0, 0x20  // STA $20f0,X always takes 5
+    ];
+
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    mem.load_rom(0x0040, &[0xff, 0x30]).unwrap();
+    mem.store(0x2030, 0x01).unwrap();
+    mem.store(0x2110, 0x02).unwrap();
+    mem.store(0x3101, 0x03).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.x = 0x20;
+    cpu.y = 0x02;
+
+    assert_eq!(4, cpu.step().unwrap());
+    assert_eq!(0x01, cpu.a);
+    assert_eq!(5, cpu.step().unwrap());
+    assert_eq!(0x02, cpu.a);
+    assert_eq!(6, cpu.step().unwrap());
+    assert_eq!(0x03, cpu.a);
+    assert_eq!(5, cpu.step().unwrap());
+}
+
+/// xorshift, so a failing run can be reproduced from the seed alone
+fn next_random(state : &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
+
+#[test]
+fn fetch_never_panics_on_random_bytes() {
+    let mut seed = 0x2600_6507;
+
+    for size in [64 * 1024, 0x1000, 3] {
+        let mut mem = Memory::with_capacity(size).unwrap();
+        let bytes : Vec<u8> = (0..size).map(|_| next_random(&mut seed) as u8).collect();
+        mem.load_rom(0x0000, &bytes).unwrap();
+        let mut cpu = Cpu::new(mem);
+        cpu.x = next_random(&mut seed) as u8;
+        cpu.y = next_random(&mut seed) as u8;
+
+        // every start address, plus some past the end of the smaller memories
+        for start in 0..=0xffff_u16 {
+            cpu.pc = start;
+            if cpu.fetch().is_ok() {
+                let size = cpu.pc.wrapping_sub(start);
+                assert!((1..=3).contains(&size), "{:04X} decoded as {} bytes", start, size);
+            }
+        }
+    }
+}
+
+#[test]
+fn increments_and_decrements_index_registers() {
+    let rom = vec![
+        0xe8, // INX
+        0xc8, // INY
+        0xca, // DEX
+        0x88  // DEY
+    ];
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.x = 0xff;
+    cpu.y = 0x7f;
+
+    assert_eq!(2, cpu.step().unwrap());
+    assert_eq!(0x00, cpu.x);
+    assert!(cpu.get_flag(CpuFlags::Zero));
+
+    cpu.step().unwrap();
+    assert_eq!(0x80, cpu.y);
+    assert!(cpu.get_flag(CpuFlags::Negative));
+    assert!(!cpu.get_flag(CpuFlags::Zero));
+
+    cpu.step().unwrap();
+    assert_eq!(0xff, cpu.x);
+    assert!(cpu.get_flag(CpuFlags::Negative));
+
+    cpu.step().unwrap();
+    assert_eq!(0x7f, cpu.y);
+    assert!(!cpu.get_flag(CpuFlags::Negative));
+}
+
+#[test]
+fn indexed_stores_cost_the_same_across_pages() {
+    let rom = vec![
+        0x91, 0x40,       // STA ($40),Y stays in page 0x30
+        0x91, 0x42,       // STA ($42),Y crosses into 0x31
+        0x9d, 0x00, 0x20, // STA $2000,X stays
+        0x9d, 0xf0, 0x20, // STA $20f0,X crosses
+        0x99, 0x00, 0x20, // STA $2000,Y stays
+        0x99, 0xf0, 0x20  // STA $20f0,Y crosses
+    ];
+
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    mem.load_rom(0x0040, &[0x00, 0x30, 0xff, 0x30]).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.a = 0x5a;
+    cpu.x = 0x20;
+    cpu.y = 0x20;
+
+    assert_eq!(6, cpu.step().unwrap());
+    assert_eq!(6, cpu.step().unwrap());
+    assert_eq!(0x5a, cpu.memory.load(0x311f).unwrap());
+    for _ in 0..4 {
+        assert_eq!(5, cpu.step().unwrap());
+    }
+    assert_eq!(0x5a, cpu.memory.load(0x2110).unwrap());
+}
+
+#[test]
+fn stores_reject_operands_without_an_address() {
+    let mem = Memory::new(64*1024).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    for addressing in [Addressing::Immediate(0x10), Addressing::Implied] {
+        let instruction = Instruction {
+            operation: Operations::StoreAccumulator,
+            addressing,
+            cycle_count: 2
+        };
+        assert!(matches!(cpu.execute(instruction), Err(CpuError::InvalidAddressModeDerefenced)));
+    }
+    assert_eq!(0x00, cpu.memory.load(0x0010).unwrap());
+}
+
+#[test]
+fn cloned_cpus_run_independently() {
+    let rom = vec![
+        0xa9, 0x42, // LDA #$42
+        0x85, 0x80  // STA $80
+    ];
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.step().unwrap();
+
+    let mut fork = cpu.clone();
+    fork.step().unwrap();
+
+    assert_eq!(0x42, fork.memory.load(0x0080).unwrap());
+    assert_eq!(0x1004, fork.pc);
+    assert_eq!(0x00, cpu.memory.load(0x0080).unwrap());
+    assert_eq!(0x1002, cpu.pc);
+}
+
+#[test]
+fn one_byte_opcodes_decode_at_the_end_of_memory() {
+    let mut mem = Memory::with_capacity(0x100).unwrap();
+    mem.store(0x00ff, 0xea).unwrap(); // NOP
+    let mut cpu = Cpu::new(mem);
+
+    let (instruction, size) = cpu.decode_at(0x00ff).unwrap();
+    assert_eq!(Operations::NoOperation, instruction.operation);
+    assert_eq!(1, size);
+
+    // none of the one byte instructions should look for operands past the end
+    for opcode in 0..=0xff {
+        if opcode_info(opcode).is_some_and(|info| info.bytes == 1) {
+            cpu.memory.store(0x00ff, opcode).unwrap();
+            assert!(cpu.decode_at(0x00ff).is_ok(), "{:02x}", opcode);
+        }
+    }
+}
+
+#[test]
+fn runs_a_single_opcode() {
+    let mem = Memory::new(64*1024).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    assert_eq!(2, cpu.run_opcode(0xa9, &[0x42]).unwrap()); // LDA #$42
+    assert_eq!(0x42, cpu.a);
+    assert_eq!(0x1002, cpu.pc);
+
+    assert_eq!(3, cpu.run_opcode(0x85, &[0x80]).unwrap()); // STA $80
+    assert_eq!(0x42, cpu.memory.load(0x0080).unwrap());
+}
+
+#[test]
+fn memory_is_reachable_through_the_cpu() {
+    let mut cpu = Cpu::new(Memory::new(64*1024).unwrap());
+    cpu.memory_mut().load_rom(0x1000, &[0xa9, 0x42, 0x85, 0x80]).unwrap(); // LDA #$42, STA $80
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(0x42, cpu.memory().load(0x0080).unwrap());
+}
+
+#[test]
+fn reset_reports_a_missing_vector() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.store_word(0xfffc, 0x1234).unwrap();
+    let mut cpu = Cpu::new(mem);
+    cpu.reset().unwrap();
+    assert_eq!(0x1234, cpu.pc());
+
+    let mut cpu = Cpu::new(Memory::with_capacity(0x100).unwrap());
+    assert!(matches!(cpu.reset(), Err(CpuError::MemoryBoundsError(_))));
+    assert_eq!(0x1000, cpu.pc());
+}
+
+#[test]
+fn bit_takes_n_and_v_from_the_operand() {
+    let rom = vec![
+        0x24, 0x80,       // BIT $80
+        0x24, 0x80,       // BIT $80
+        0x2c, 0x81, 0x00  // BIT $0081
+    ];
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    mem.load_rom(0x0080, &[0xc0, 0x40]).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    cpu.a = 0x01;
+    assert_eq!(3, cpu.step().unwrap());
+    assert!(cpu.get_flag(CpuFlags::Zero));
+    assert!(cpu.get_flag(CpuFlags::Negative));
+    assert!(cpu.get_flag(CpuFlags::Overflow));
+
+    cpu.a = 0xc0;
+    cpu.step().unwrap();
+    assert!(!cpu.get_flag(CpuFlags::Zero));
+    assert!(cpu.get_flag(CpuFlags::Negative));
+    assert!(cpu.get_flag(CpuFlags::Overflow));
+    assert_eq!(0xc0, cpu.a);
+
+    // bit 6 alone: V without N, and the AND is empty
+    cpu.a = 0x80;
+    assert_eq!(4, cpu.step().unwrap());
+    assert!(cpu.get_flag(CpuFlags::Zero));
+    assert!(!cpu.get_flag(CpuFlags::Negative));
+    assert!(cpu.get_flag(CpuFlags::Overflow));
+}
+
+#[test]
+fn logic_operations_combine_into_a() {
+    let rom = vec![
+        0x29, 0x0f,       // AND #$0f
+        0x09, 0x80,       // ORA #$80
+        0x49, 0x8a,       // EOR #$8a
+        0x3d, 0xff, 0x20  // AND $20ff,X
+    ];
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0x1000, &rom).unwrap();
+    mem.load_rom(0x2100, &[0x00]).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    cpu.a = 0x5a;
+    assert_eq!(2, cpu.step().unwrap());
+    assert_eq!(0x0a, cpu.a);
+    assert!(!cpu.get_flag(CpuFlags::Negative));
+
+    cpu.step().unwrap();
+    assert_eq!(0x8a, cpu.a);
+    assert!(cpu.get_flag(CpuFlags::Negative));
+
+    cpu.step().unwrap();
+    assert_eq!(0x00, cpu.a);
+    assert!(cpu.get_flag(CpuFlags::Zero));
+
+    // the page cross costs a cycle, like the other reads
+    cpu.a = 0xff;
+    cpu.x = 0x01;
+    assert_eq!(5, cpu.step().unwrap());
+    assert_eq!(0x00, cpu.a);
+    assert!(cpu.get_flag(CpuFlags::Zero));
+}
+
+#[test]
+fn pushes_land_where_the_stack_pointer_was_set() {
+    let mut cpu = Cpu::new(Memory::new(64*1024).unwrap());
+    cpu.set_sp(0x80);
+    cpu.a = 0x42;
+
+    cpu.run_opcode(0x48, &[]).unwrap(); // PHA
+    assert_eq!(0x42, cpu.memory().load(0x0180).unwrap());
+    assert_eq!(0x7f, cpu.sp());
+}
+
+#[test]
+fn pc_runs_off_the_end_of_a_small_memory() {
+    let mut mem = Memory::new(16*1024).unwrap();
+    mem.load_rom(0x3fff, &[0xea]).unwrap(); // NOP
+    let mut cpu = Cpu::builder(mem).pc(0x3fff).build();
+
+    cpu.step().unwrap();
+    assert_eq!(0x4000, cpu.pc);
+    assert!(matches!(cpu.step(), Err(CpuError::MemoryBoundsError(_))));
+}
+
+#[test]
+fn pc_wraps_at_the_end_of_a_small_memory() {
+    let mut mem = Memory::new(16*1024).unwrap();
+    mem.load_rom(0x3fff, &[0xea]).unwrap(); // NOP
+    mem.load_rom(0x0000, &[0xa9, 0x42]).unwrap(); // LDA #$42
+    let mut cpu = Cpu::builder(mem).pc(0x3fff).build();
+    cpu.set_pc_wrap(true);
+
+    cpu.step().unwrap();
+    assert_eq!(0x0000, cpu.pc);
+    cpu.step().unwrap();
+    assert_eq!(0x42, cpu.a);
+    assert_eq!(0x0002, cpu.pc);
+}
+
+#[test]
+fn pc_wraps_around_a_64k_memory_regardless() {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0xffff, &[0xea]).unwrap(); // NOP
+    let mut cpu = Cpu::builder(mem).pc(0xffff).build();
+
+    cpu.step().unwrap();
+    assert_eq!(0x0000, cpu.pc);
+}
+
+#[test]
+fn tax_only_touches_n_and_z() {
+    let mut cpu = Cpu::new(Memory::new(64*1024).unwrap());
+    cpu.set_flag(CpuFlags::Carry);
+    cpu.set_flag(CpuFlags::Overflow);
+    cpu.set_flag(CpuFlags::Decimal);
+
+    cpu.a = 0x80;
+    cpu.run_opcode(0xaa, &[]).unwrap(); // TAX
+    assert_eq!(0x80, cpu.x);
+    assert!(cpu.get_flag(CpuFlags::Negative));
+    assert!(!cpu.get_flag(CpuFlags::Zero));
+    assert!(cpu.get_flag(CpuFlags::Carry));
+    assert!(cpu.get_flag(CpuFlags::Overflow));
+    assert!(cpu.get_flag(CpuFlags::Decimal));
+
+    cpu.a = 0x00;
+    cpu.run_opcode(0xaa, &[]).unwrap(); // TAX
+    assert_eq!(0x00, cpu.x);
+    assert!(!cpu.get_flag(CpuFlags::Negative));
+    assert!(cpu.get_flag(CpuFlags::Zero));
+    assert!(cpu.get_flag(CpuFlags::Carry));
+    assert!(cpu.get_flag(CpuFlags::Overflow));
+    assert!(cpu.get_flag(CpuFlags::Decimal));
+}
+
+#[test]
+fn transfers_copy_registers() {
+    let mut cpu = Cpu::new(Memory::new(64*1024).unwrap());
+
+    cpu.a = 0x12;
+    assert_eq!(2, cpu.run_opcode(0xa8, &[]).unwrap()); // TAY
+    assert_eq!(0x12, cpu.y);
+
+    cpu.x = 0x34;
+    cpu.run_opcode(0x8a, &[]).unwrap(); // TXA
+    assert_eq!(0x34, cpu.a);
+
+    cpu.y = 0xf0;
+    cpu.run_opcode(0x98, &[]).unwrap(); // TYA
+    assert_eq!(0xf0, cpu.a);
+    assert!(cpu.get_flag(CpuFlags::Negative));
+
+    cpu.sp = 0xfd;
+    cpu.run_opcode(0xba, &[]).unwrap(); // TSX
+    assert_eq!(0xfd, cpu.x);
+
+    // TXS is the one transfer that doesn't set N and Z
+    cpu.x = 0x00;
+    cpu.run_opcode(0x9a, &[]).unwrap(); // TXS
+    assert_eq!(0x00, cpu.sp);
+    assert!(!cpu.get_flag(CpuFlags::Zero));
+    assert!(cpu.get_flag(CpuFlags::Negative));
+}
+
+/// base cycles of the documented opcodes, from the MOS datasheet. 0 marks the illegal ones
+const REFERENCE_CYCLES : [u16; 256] = [
+//  0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f
+    7, 6, 0, 0, 0, 3, 5, 0, 3, 2, 2, 0, 0, 4, 6, 0, // 0
+    2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0, // 1
+    6, 6, 0, 0, 3, 3, 5, 0, 4, 2, 2, 0, 4, 4, 6, 0, // 2
+    2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0, // 3
+    6, 6, 0, 0, 0, 3, 5, 0, 3, 2, 2, 0, 3, 4, 6, 0, // 4
+    2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0, // 5
+    6, 6, 0, 0, 0, 3, 5, 0, 4, 2, 2, 0, 5, 4, 6, 0, // 6
+    2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0, // 7
+    0, 6, 0, 0, 3, 3, 3, 0, 2, 0, 2, 0, 4, 4, 4, 0, // 8
+    2, 6, 0, 0, 4, 4, 4, 0, 2, 5, 2, 0, 0, 5, 0, 0, // 9
+    2, 6, 2, 0, 3, 3, 3, 0, 2, 2, 2, 0, 4, 4, 4, 0, // a
+    2, 5, 0, 0, 4, 4, 4, 0, 2, 4, 2, 0, 4, 4, 4, 0, // b
+    2, 6, 0, 0, 3, 3, 5, 0, 2, 2, 2, 0, 4, 4, 6, 0, // c
+    2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0, // d
+    2, 6, 0, 0, 3, 3, 5, 0, 2, 2, 2, 0, 4, 4, 6, 0, // e
+    2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0  // f
+];
+
+#[test]
+fn base_cycles_match_the_reference() {
+    let mut wrong = Vec::new();
+    for opcode in 0..=0xffu8 {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &[opcode]).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        match (cpu.fetch(), REFERENCE_CYCLES[opcode as usize]) {
+            (Err(_), 0) => (),
+            (Ok(instruction), cycles) if instruction.cycle_count == cycles => (),
+            (result, cycles) => wrong.push(format!("{:02x}: expected {}, got {:?}", opcode,
+                cycles, result.map(|instruction| instruction.cycle_count)))
+        }
+    }
+    assert!(wrong.is_empty(), "{:#?}", wrong);
+}
+
+/// bytes taken by the documented opcodes, operands included. 0 marks the illegal ones
+const REFERENCE_SIZES : [u16; 256] = [
+//  0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f
+    1, 2, 0, 0, 0, 2, 2, 0, 1, 2, 1, 0, 0, 3, 3, 0, // 0
+    2, 2, 0, 0, 0, 2, 2, 0, 1, 3, 0, 0, 0, 3, 3, 0, // 1
+    3, 2, 0, 0, 2, 2, 2, 0, 1, 2, 1, 0, 3, 3, 3, 0, // 2
+    2, 2, 0, 0, 0, 2, 2, 0, 1, 3, 0, 0, 0, 3, 3, 0, // 3
+    1, 2, 0, 0, 0, 2, 2, 0, 1, 2, 1, 0, 3, 3, 3, 0, // 4
+    2, 2, 0, 0, 0, 2, 2, 0, 1, 3, 0, 0, 0, 3, 3, 0, // 5
+    1, 2, 0, 0, 0, 2, 2, 0, 1, 2, 1, 0, 3, 3, 3, 0, // 6
+    2, 2, 0, 0, 0, 2, 2, 0, 1, 3, 0, 0, 0, 3, 3, 0, // 7
+    0, 2, 0, 0, 2, 2, 2, 0, 1, 0, 1, 0, 3, 3, 3, 0, // 8
+    2, 2, 0, 0, 2, 2, 2, 0, 1, 3, 1, 0, 0, 3, 0, 0, // 9
+    2, 2, 2, 0, 2, 2, 2, 0, 1, 2, 1, 0, 3, 3, 3, 0, // a
+    2, 2, 0, 0, 2, 2, 2, 0, 1, 3, 1, 0, 3, 3, 3, 0, // b
+    2, 2, 0, 0, 2, 2, 2, 0, 1, 2, 1, 0, 3, 3, 3, 0, // c
+    2, 2, 0, 0, 0, 2, 2, 0, 1, 3, 0, 0, 0, 3, 3, 0, // d
+    2, 2, 0, 0, 2, 2, 2, 0, 1, 2, 1, 0, 3, 3, 3, 0, // e
+    2, 2, 0, 0, 0, 2, 2, 0, 1, 3, 0, 0, 0, 3, 3, 0  // f
+];
+
+#[test]
+fn fetch_advances_pc_by_the_reference_size() {
+    let mut wrong = Vec::new();
+    for opcode in 0..=0xffu8 {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0x1000, &[opcode]).unwrap();
+        let mut cpu = Cpu::new(mem);
+
+        let size = REFERENCE_SIZES[opcode as usize];
+        match cpu.fetch() {
+            Err(_) if size == 0 => (),
+            Ok(_) if cpu.pc == 0x1000 + size => (),
+            result => wrong.push(format!("{:02x}: expected {} bytes, got {:?}", opcode,
+                size, result.map(|_| cpu.pc - 0x1000)))
+        }
+    }
+    assert!(wrong.is_empty(), "{:#?}", wrong);
+}
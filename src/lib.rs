@@ -0,0 +1,7 @@
+pub mod bus;
+pub mod memory;
+pub mod riot;
+pub mod tia;
+pub mod atari_bus;
+pub mod cpu;
+pub mod assemble;
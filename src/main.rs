@@ -1,10 +1,75 @@
-use memory::Memory;
-use cpu::Cpu;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
 
-mod memory;
-mod cpu;
+use ataritron::memory::Memory;
+use ataritron::cpu::{Cpu, RunResult};
 
-fn main() {
-    let mem = Memory::new(64*1024).unwrap();
-    let _cpu = Cpu::new(mem);
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// about 8 seconds of 2600 time, for ROMs that never reach a BRK
+const MAX_CYCLES : u64 = 10_000_000;
+
+fn main() -> Result<()> {
+    let args : Vec<String> = env::args().collect();
+    run_main(&args, &mut io::stdout())
+}
+
+/// loads the ROM image named in `args` so it ends at 0xffff, where the vectors are, runs it
+/// from the reset vector until a BRK, then writes the registers to `out`
+fn run_main(args : &[String], out : &mut impl Write) -> Result<()> {
+    let path = args.get(1).ok_or("usage: ataritron <rom>")?;
+    let rom = fs::read(path)?;
+    if rom.is_empty() || rom.len() > 0x10000 {
+        return Err(format!("{} is not a ROM image of 1 to 65536 bytes", path).into());
+    }
+
+    let mut mem = Memory::new(64*1024)?;
+    let start = (0x10000 - rom.len()) as u16;
+    mem.load_rom(start, &rom)?;
+    mem.set_rom_region(start, 0xffff);
+    let mut cpu = Cpu::new_from_reset(mem)?;
+    let result = cpu.run_until_break(MAX_CYCLES)?;
+
+    let state = cpu.state();
+    writeln!(out, "PC={:04X} A={:02X} X={:02X} Y={:02X} SP={:02X} SR={:02X} cycles={}",
+        state.pc, state.a, state.x, state.y, state.sp, state.sr, result.cycles())?;
+    if let RunResult::CycleCapReached { .. } = result {
+        writeln!(out, "stopped after {} cycles without reaching a BRK", MAX_CYCLES)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_a_rom_until_brk() {
+        let mut image = vec![0x00; 0x1000];
+        image[..5].copy_from_slice(&[
+            0xa9, 0x42, // LDA #$42
+            0xa2, 0x07, // LDX #$07
+            0x00        // BRK
+        ]);
+        image[0xffc..0xffe].copy_from_slice(&[0x00, 0xf0]); // reset vector, the image starts at 0xf000
+        let path = env::temp_dir().join(format!("ataritron-main-{}.bin", std::process::id()));
+        fs::write(&path, &image).unwrap();
+
+        let mut out = Vec::new();
+        let result = run_main(&[String::from("ataritron"), path.to_str().unwrap().to_string()], &mut out);
+        fs::remove_file(&path).unwrap();
+        result.unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("PC=F004 A=42 X=07 Y=00"), "{}", out);
+    }
+
+    #[test]
+    fn needs_a_rom() {
+        let mut out = Vec::new();
+        assert!(run_main(&[String::from("ataritron")], &mut out).is_err());
+        assert!(out.is_empty());
+    }
 }
@@ -1,24 +1,132 @@
+mod cartridge;
+mod poison;
+
 use core::fmt;
+use std::cell::RefCell;
 use std::error::Error;
+use std::ops::RangeInclusive;
+
+use crate::atari_bus::AtariAddress;
+use crate::bus::{Bus, MemoryMappedDevice};
+use crate::riot::Riot;
+use crate::tia::Tia;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub use self::cartridge::{Cartridge, CartridgeError, CARTRIDGE_END, CARTRIDGE_START};
+pub use self::poison::UninitializedReadHook;
+use self::poison::Poison;
 
 #[derive(Debug)]
 pub struct OutOfRangeError {
     value : u32,
     min : u32,
     max : u32,
+    /// the value is inside [min, max], which is a read-only region
+    read_only : bool,
+}
+
+impl OutOfRangeError {
+    fn read_only(addr : u16, region : &RangeInclusive<u16>) -> OutOfRangeError {
+        OutOfRangeError {
+            value: addr as u32,
+            min: *region.start() as u32,
+            max: *region.end() as u32,
+            read_only: true
+        }
+    }
+
+    /// the address that was rejected. for a word that's the byte that failed, so a word
+    /// whose high byte is past the end reports the address after `addr`
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    /// whether this was a write to read-only memory rather than an invalid address
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
 }
 
 impl fmt::Display for OutOfRangeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Value {} is outside of allowed range [{}, {}]", self.value, self.min, self.max)
+        if self.read_only {
+            write!(f, "Value {} is inside of read-only range [{}, {}]", self.value, self.min, self.max)
+        } else {
+            write!(f, "Value {} is outside of allowed range [{}, {}]", self.value, self.min, self.max)
+        }
     }
 }
 
 impl Error for OutOfRangeError {}
 
+/// how addresses are decoded before indexing the backing storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AddressMapping {
+    /// every address is its own byte
+    Flat,
+    /// the 2600 only decodes some address lines, so TIA, RAM and RIOT show up
+    /// in several places of the low 8K
+    Atari2600
+}
+
+/// what a store to a read-only region does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RomWritePolicy {
+    /// the write is dropped, like on the real hardware
+    Ignore,
+    /// the write fails with an error that reports `is_read_only()`
+    Error
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Memory {
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
     data : Vec<u8>,
-    size : u32
+    size : u32,
+    mapping : AddressMapping,
+    rom_region : Option<RangeInclusive<u16>>,
+    rom_write_policy : RomWritePolicy,
+    /// only A0-A12 reach the bus, like on the 6507, see `set_6507_addressing`
+    #[cfg_attr(feature = "serde", serde(default))]
+    thirteen_bit_bus : bool,
+    /// owns the RIOT registers (0x280-0x29f) when decoding like the 2600
+    riot : Option<Riot>,
+    /// the device behind the TIA registers (0x00-0x3f). reads can change a device's state,
+    /// so it sits in a cell. it's not part of a serialized memory
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tia : Option<RefCell<Box<dyn MemoryMappedDevice>>>,
+    /// answers reads of the cartridge window instead of `data` when present
+    cartridge : Option<Cartridge>,
+    /// what RAM is filled with on `clear_ram`, when it's not zeros
+    #[cfg_attr(feature = "serde", serde(skip))]
+    poison : Option<Poison>,
+    /// called from loads, which only borrow the memory
+    #[cfg_attr(feature = "serde", serde(skip))]
+    uninitialized_read_hook : Option<RefCell<UninitializedReadHook>>
+}
+
+/// a copy with its own RAM and devices, for forking the machine's state.
+/// the uninitialized read hook can't be copied, so the copy stops warning
+impl Clone for Memory {
+    fn clone(&self) -> Memory {
+        Memory {
+            data: self.data.clone(),
+            size: self.size,
+            mapping: self.mapping,
+            rom_region: self.rom_region.clone(),
+            rom_write_policy: self.rom_write_policy,
+            thirteen_bit_bus: self.thirteen_bit_bus,
+            riot: self.riot.clone(),
+            tia: self.tia.as_ref().map(|tia| RefCell::new(tia.borrow().clone_device())),
+            cartridge: self.cartridge.clone(),
+            poison: self.poison.clone(),
+            uninitialized_read_hook: None
+        }
+    }
 }
 
 impl Memory {
@@ -33,53 +141,413 @@ impl Memory {
             return Err(OutOfRangeError {
                 value: size,
                 min: 13824,
-                max: 0xffff+1
+                max: 0xffff+1,
+                read_only: false
+            })
+        }
+
+        Memory::with_capacity(size)
+    }
+
+    /// like `new` but without the 2600 floor, so tests can get by with a page or two
+    pub fn with_capacity(size : u32) -> Result<Memory, OutOfRangeError> {
+        if !(1..=0xffff + 1).contains(&size) {
+            return Err(OutOfRangeError {
+                value: size,
+                min: 1,
+                max: 0xffff+1,
+                read_only: false
             })
         }
 
         Ok(Memory {
             data: vec![0x00; size as usize],
-            size
+            size,
+            mapping: AddressMapping::Flat,
+            rom_region: None,
+            rom_write_policy: RomWritePolicy::Ignore,
+            thirteen_bit_bus: false,
+            riot: None,
+            tia: None,
+            cartridge: None,
+            poison: None,
+            uninitialized_read_hook: None
         })
     }
 
+    /// a full 64K memory decoding addresses like the 2600 does, so mirrors of the
+    /// TIA registers, RAM and RIOT all land on the same byte
+    pub fn new_atari() -> Memory {
+        let mut mem = Memory::new(0xffff + 1).unwrap();
+        mem.mapping = AddressMapping::Atari2600;
+        mem.riot = Some(Riot::new());
+        mem.tia = Some(RefCell::new(Box::new(Tia::new())));
+        mem
+    }
+
+    /// bytes of backing storage
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// the highest address with storage behind it
+    pub fn last_addr(&self) -> u16 {
+        (self.size - 1) as u16
+    }
+
+    pub(crate) fn riot(&self) -> Option<&Riot> {
+        self.riot.as_ref()
+    }
+
+    /// the RIOT, only present when decoding like the 2600
+    pub fn riot_mut(&mut self) -> Option<&mut Riot> {
+        self.riot.as_mut()
+    }
+
+    /// the device behind the TIA registers, for save states
+    pub(crate) fn tia(&self) -> Option<&RefCell<Box<dyn MemoryMappedDevice>>> {
+        self.tia.as_ref()
+    }
+
+    /// puts `device` behind the TIA registers, replacing the stub `new_atari` installs.
+    /// only reached when decoding like the 2600
+    pub fn set_tia(&mut self, device : Box<dyn MemoryMappedDevice>) {
+        self.tia = Some(RefCell::new(device));
+    }
+
+    /// the chip an (already 13-bit) address reaches when decoding like the 2600.
+    /// the 6502's extra address lines go to plain memory
+    fn atari_address(&self, addr : u16) -> Option<AtariAddress> {
+        match self.mapping {
+            AddressMapping::Atari2600 if addr < 0x2000 => Some(AtariAddress::decode(addr)),
+            _ => None
+        }
+    }
+
+    /// the TIA register an address refers to, if any
+    fn tia_register(&self, addr : u16) -> Option<u8> {
+        match (&self.tia, self.atari_address(addr)) {
+            (Some(_), Some(AtariAddress::Tia(reg))) => Some(reg),
+            _ => None
+        }
+    }
+
+    /// the RIOT register an address refers to, if any
+    fn riot_register(&self, addr : u16) -> Option<u8> {
+        match (&self.riot, self.atari_address(addr)) {
+            (Some(_), Some(AtariAddress::Riot(reg))) => Some(reg),
+            _ => None
+        }
+    }
+
+    /// folds mirrored addresses onto the address actually used for storage
+    fn canonical_address(&self, addr : u16) -> u16 {
+        let addr = if self.thirteen_bit_bus { addr & 0x1fff } else { addr };
+
+        match self.atari_address(addr) {
+            None | Some(AtariAddress::Cartridge(_)) => addr,
+            Some(AtariAddress::Tia(reg)) => reg as u16,
+            Some(AtariAddress::Ram(byte)) => 0x0080 | byte as u16,
+            Some(AtariAddress::Riot(reg)) => 0x0280 | reg as u16
+        }
+    }
+
     pub fn load(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+        self.read(addr, true)
+    }
+
+    /// `load` without the side effects: the cartridge keeps its bank and reading RAM nobody
+    /// wrote doesn't call the uninitialized read hook
+    pub fn peek(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+        self.read(addr, false)
+    }
+
+    fn read(&self, addr : u16, side_effects : bool) -> Result<u8, OutOfRangeError> {
+        let addr = self.canonical_address(addr);
+        if let (Some(reg), Some(riot)) = (self.riot_register(addr), &self.riot) {
+            return Ok(riot.read(reg));
+        }
+        if let (Some(reg), Some(tia)) = (self.tia_register(addr), &self.tia) {
+            return Ok(tia.borrow_mut().read(reg));
+        }
+        if side_effects {
+            self.touch_cartridge(addr);
+        }
+        if let Some(byte) = self.cartridge_byte(addr) {
+            return Ok(byte);
+        }
+
         if Memory::address_in_bounds(addr, self.size) {
+            if side_effects {
+                self.check_initialized(addr);
+            }
             Ok(self.data[addr as usize])
         } else {
             Err(OutOfRangeError {
                 value: addr as u32,
                 min: 0x0,
-                max: (self.size - 1) as u32
+                max: self.size - 1,
+                read_only: false
             })
         }
     }
 
+    /// where a block of `len` bytes starting at `start` ends, if it fits in the memory
+    fn block_end(&self, start : u16, len : usize) -> Result<usize, OutOfRangeError> {
+        let end = start as usize + len;
+        if end > self.size as usize {
+            return Err(OutOfRangeError {
+                value: (end - 1) as u32,
+                min: 0x0,
+                max: self.size - 1,
+                read_only: false
+            });
+        }
+
+        Ok(end)
+    }
+
+    /// borrows `len` bytes starting at `start`. reads the storage directly, so
+    /// mirrors and the RIOT are not decoded
+    pub fn load_slice(&self, start : u16, len : usize) -> Result<&[u8], OutOfRangeError> {
+        let end = self.block_end(start, len)?;
+
+        Ok(&self.data[start as usize..end])
+    }
+
+    /// hex dump of [start, end], 16 bytes per line with their ascii, like
+    /// `0200: 48 69 00 ...  |Hi.|`. reads the storage directly like `load_slice`.
+    /// `end` is clamped to the memory size, and nothing is dumped when `start` is past it
+    pub fn dump(&self, start : u16, end : u16) -> String {
+        let end = end.min(self.last_addr()) as usize;
+        if start as usize > end {
+            return String::new();
+        }
+
+        let mut text = String::new();
+        for (i, line) in self.data[start as usize..=end].chunks(16).enumerate() {
+            let hex : Vec<String> = line.iter().map(|byte| format!("{:02X}", byte)).collect();
+            let ascii : String = line.iter()
+                .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+                .collect();
+
+            text.push_str(&format!("{:04X}: {:<47}  |{}|\n", start as usize + i * 16, hex.join(" "), ascii));
+        }
+
+        text
+    }
+
+    /// marks [start, end] as read-only, replacing any previous region.
+    /// `load_rom` can still write there
+    pub fn set_rom_region(&mut self, start : u16, end : u16) {
+        self.rom_region = Some(start..=end);
+    }
+
+    pub fn set_rom_write_policy(&mut self, policy : RomWritePolicy) {
+        self.rom_write_policy = policy;
+    }
+
+    /// the 6507 only has 13 address lines, so with this on every address folds into the
+    /// first 8K before being decoded. that's how the reset vector at 0xfffc ends up in the cartridge
+    pub fn set_6507_addressing(&mut self, enabled : bool) {
+        self.thirteen_bit_bus = enabled;
+    }
+
     pub fn store(&mut self, addr : u16, byte : u8) -> Result<(), OutOfRangeError> {
+        let addr = self.canonical_address(addr);
+        self.touch_cartridge(addr);
+
+        if let Some(region) = &self.rom_region {
+            if region.contains(&addr) {
+                return match self.rom_write_policy {
+                    RomWritePolicy::Ignore => Ok(()),
+                    RomWritePolicy::Error => Err(OutOfRangeError::read_only(addr, region))
+                }
+            }
+        }
+
+        if let Some(reg) = self.riot_register(addr) {
+            self.riot.as_mut().unwrap().write(reg, byte);
+            return Ok(());
+        }
+        if let Some(reg) = self.tia_register(addr) {
+            self.tia.as_ref().unwrap().borrow_mut().write(reg, byte);
+            return Ok(());
+        }
+
+        self.write(addr, byte)
+    }
+
+    /// stores a little-endian word, low byte first. the high byte of a word at 0xffff goes to 0x0000,
+    /// where the cpu's word reads look for it too
+    pub fn store_word(&mut self, addr : u16, value : u16) -> Result<(), OutOfRangeError> {
+        let [low, high] = value.to_le_bytes();
+        self.store(addr, low)?;
+        self.store(addr.wrapping_add(1), high)
+    }
+
+    /// stores ignoring the read-only region
+    fn write(&mut self, addr : u16, byte : u8) -> Result<(), OutOfRangeError> {
+        let addr = self.canonical_address(addr);
         if Memory::address_in_bounds(addr, self.size) {
             self.data[addr as usize] = byte;
+            self.mark_written(addr);
             return Ok(())
         }
 
         Err(OutOfRangeError {
             value: addr as u32,
             min: 0x0,
-            max: (self.size - 1) as u32
+            max: self.size - 1,
+            read_only: false
         })
     }
     
-    pub fn load_rom(&mut self, addr : u16, rom: &[u8]) {
+    /// zeroes RAM, or fills it with the poison pattern. on the 2600 that's the 128 bytes in the
+    /// RIOT; flat memory is all RAM except the read-only region, so anything loaded outside
+    /// `rom_region` goes too
+    pub fn clear_ram(&mut self) {
+        let fill = self.ram_fill();
+        for addr in 0..self.data.len() {
+            let addr = addr as u16;
+            if self.is_ram(addr) {
+                self.data[addr as usize] = fill;
+                self.mark_poisoned(addr);
+            }
+        }
+    }
+
+    fn is_ram(&self, addr : u16) -> bool {
+        match self.mapping {
+            AddressMapping::Atari2600 => (0x80..=0xff).contains(&addr),
+            AddressMapping::Flat => !self.rom_region.as_ref().is_some_and(|region| region.contains(&addr))
+        }
+    }
+
+    /// the whole backing storage, used by save states
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub(crate) fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// copies `rom` to memory starting at `addr`, ignoring the read-only region.
+    /// nothing is written if it doesn't fit
+    pub fn load_rom(&mut self, addr : u16, rom: &[u8]) -> Result<(), OutOfRangeError> {
+        self.block_end(addr, rom.len())?;
+
         for (i, byte) in rom.iter().enumerate() {
-            self.store(addr + (i as u16), *byte).unwrap();
+            self.write(addr + (i as u16), *byte)?;
+        }
+
+        Ok(())
+    }
+
+    /// like `load_rom`, but returns the address right after the block so the next one can follow it.
+    /// a block ending at 0xffff gives 0x0000
+    pub fn load_rom_at_org(&mut self, org : u16, rom : &[u8]) -> Result<u16, OutOfRangeError> {
+        self.load_rom(org, rom)?;
+        Ok(org.wrapping_add(rom.len() as u16))
+    }
+
+    /// loads several blocks at once, like code, data and vectors. every block is checked
+    /// before anything is written, so an error leaves the memory untouched
+    pub fn load_segments(&mut self, segments : &[(u16, &[u8])]) -> Result<(), OutOfRangeError> {
+        for (addr, rom) in segments {
+            self.block_end(*addr, rom.len())?;
+        }
+
+        for (addr, rom) in segments {
+            self.load_rom(*addr, rom)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Bus for Memory {
+    fn load(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+        Memory::load(self, addr)
+    }
+
+    fn store(&mut self, addr : u16, v : u8) -> Result<(), OutOfRangeError> {
+        Memory::store(self, addr, v)
+    }
+
+    fn peek(&self, addr : u16) -> Result<u8, OutOfRangeError> {
+        Memory::peek(self, addr)
+    }
+
+    fn tick(&mut self, cycles : u8) {
+        if let Some(riot) = self.riot.as_mut() {
+            riot.tick(cycles);
+        }
+        if let Some(tia) = self.tia.as_mut() {
+            tia.get_mut().tick(cycles);
+        }
+    }
+
+    fn ready(&self) -> bool {
+        self.tia.as_ref().is_none_or(|tia| tia.borrow().ready())
+    }
+
+    fn clear_ram(&mut self) {
+        Memory::clear_ram(self)
+    }
+
+    fn reset_devices(&mut self) {
+        if let Some(riot) = self.riot.as_mut() {
+            riot.reset();
+        }
+        if let Some(tia) = self.tia.as_mut() {
+            tia.get_mut().reset();
+        }
+    }
+
+    /// only flat memories end early, the 2600 mirrors its chips all over the 16-bit space
+    fn address_space(&self) -> u32 {
+        match self.mapping {
+            AddressMapping::Flat if !self.thirteen_bit_bus => self.size,
+            _ => 0x10000
         }
     }
 }
 
+/// memory is serialized as a hex string, which is far more compact than a json array of numbers
+#[cfg(feature = "serde")]
+mod hex_bytes {
+    use std::fmt::Write;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data : &[u8], serializer : S) -> Result<S::Ok, S::Error> {
+        let mut hex = String::with_capacity(data.len() * 2);
+        for byte in data {
+            write!(hex, "{:02x}", byte).unwrap();
+        }
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer : D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(D::Error::custom("hex string has an odd length"));
+        }
+
+        (0..hex.len()).step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::unnecessary_cast)]
     fn memory_inits_to_zero_array() {
         let size = 32 * 1024; // a middle-of-the-road test case
 
@@ -133,20 +601,243 @@ mod tests {
         assert_eq!(my_value, mem.data[addr as usize]);
     }
 
+    #[test]
+    fn memory_loads_a_slice() {
+        let mut mem = Memory::new(16 * 1024).unwrap();
+        mem.load_rom(0x0200, &[0x01, 0x02, 0x03, 0x04]).unwrap();
+
+        assert_eq!(&[0x02, 0x03, 0x04], mem.load_slice(0x0201, 3).unwrap());
+        assert!(mem.load_slice(0x0201, 0).unwrap().is_empty());
+        assert_eq!(0x800, mem.load_slice(0x3800, 0x800).unwrap().len()); // right up to the end
+    }
+
+    #[test]
+    fn load_slice_errors_past_the_end() {
+        let mem = Memory::new(16 * 1024).unwrap();
+        assert!(mem.load_slice(0x3ff0, 0x11).is_err());
+        assert!(mem.load_slice(0x4000, 1).is_err());
+    }
+
+    #[test]
+    fn memory_with_capacity_fits_a_zeropage() {
+        let mut mem = Memory::with_capacity(256).unwrap();
+
+        mem.store(0x00, 0x12).unwrap();
+        mem.store(0xff, 0x34).unwrap();
+        assert_eq!(0x12, mem.load(0x00).unwrap());
+        assert_eq!(0x34, mem.load(0xff).unwrap());
+        assert!(mem.load(0x100).is_err());
+        assert!(Memory::with_capacity(0).is_err());
+        assert!(Memory::new(256).is_err());
+    }
+
+    #[test]
+    fn clones_do_not_share_ram() {
+        let mut mem = Memory::new_atari();
+        mem.store(0x0080, 0x11).unwrap();
+        mem.store(0x0009, 0x22).unwrap(); // COLUBK, in the TIA
+
+        let mut copy = mem.clone();
+        copy.store(0x0080, 0x99).unwrap();
+        copy.store(0x0009, 0x33).unwrap();
+
+        assert_eq!(0x11, mem.load(0x0080).unwrap());
+        assert_eq!(0x99, copy.load(0x0080).unwrap());
+        assert_eq!(0x22, mem.load(0x0009).unwrap());
+        assert_eq!(0x33, copy.load(0x0009).unwrap());
+    }
+
+    #[test]
+    fn the_6507_only_sees_13_address_bits() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.store(0x1000, 0x42).unwrap();
+        mem.store(0x3000, 0x24).unwrap();
+        assert_eq!(0x24, mem.load(0x3000).unwrap());
+
+        mem.set_6507_addressing(true);
+        assert_eq!(0x42, mem.load(0x3000).unwrap());
+        assert_eq!(0x42, mem.load(0xf000).unwrap());
+
+        mem.store(0xf001, 0x99).unwrap();
+        assert_eq!(0x99, mem.load(0x1001).unwrap());
+    }
+
+    #[test]
+    fn memory_loads_blocks_one_after_the_other() {
+        let mut mem = Memory::new(64 * 1024).unwrap();
+
+        let next = mem.load_rom_at_org(0x1000, &[0xa9, 0x01, 0x85, 0x80]).unwrap();
+        assert_eq!(0x1004, next);
+        let next = mem.load_rom_at_org(next, &[0x4c, 0x00, 0x10]).unwrap();
+        assert_eq!(0x1007, next);
+
+        assert_eq!(0x80, mem.load(0x1003).unwrap());
+        assert_eq!(0x4c, mem.load(0x1004).unwrap());
+        assert_eq!(0x0000, mem.load_rom_at_org(0xfffe, &[0x00, 0x10]).unwrap());
+    }
+
+    #[test]
+    fn memory_loads_segments() {
+        let mut mem = Memory::new(64 * 1024).unwrap();
+        mem.load_segments(&[
+            (0x1000, &[0xa9, 0x01]),
+            (0x0200, &[0x55]),
+            (0xfffc, &[0x00, 0x10])
+        ]).unwrap();
+
+        assert_eq!(0xa9, mem.load(0x1000).unwrap());
+        assert_eq!(0x01, mem.load(0x1001).unwrap());
+        assert_eq!(0x55, mem.load(0x0200).unwrap());
+        assert_eq!(0x10, mem.load(0xfffd).unwrap());
+    }
+
+    #[test]
+    fn memory_loads_no_segment_if_one_is_out_of_bounds() {
+        let mut mem = Memory::new(32 * 1024).unwrap();
+        let result = mem.load_segments(&[
+            (0x1000, &[0xa9, 0x01]),
+            (0x7fff, &[0x00, 0x10])
+        ]);
+
+        assert!(result.is_err());
+        assert_eq!(0x00, mem.load(0x1000).unwrap());
+        assert_eq!(0x00, mem.load(0x7fff).unwrap());
+    }
+
+    #[test]
+    fn memory_dumps_hex_and_ascii() {
+        let mut mem = Memory::new(32 * 1024).unwrap();
+        mem.load_rom(0x0200, b"Hello, 2600!\x00\x01\xffABC").unwrap();
+
+        assert_eq!(mem.dump(0x0200, 0x0212), concat!(
+            "0200: 48 65 6C 6C 6F 2C 20 32 36 30 30 21 00 01 FF 41  |Hello, 2600!...A|\n",
+            "0210: 42 43 00                                         |BC.|\n"
+        ));
+        assert_eq!(mem.dump(0x7ffe, 0xffff), "7FFE: 00 00                                            |..|\n");
+        assert_eq!(mem.dump(0x0210, 0x0200), "");
+        assert_eq!(mem.dump(0x9000, 0xffff), "");
+    }
+
+    #[test]
+    fn memory_reports_its_size() {
+        let mem = Memory::new(16 * 1024).unwrap();
+        assert_eq!(16384, mem.size());
+        assert_eq!(0x3fff, mem.last_addr());
+
+        let mem = Memory::new(64 * 1024).unwrap();
+        assert_eq!(0xffff, mem.last_addr());
+    }
+
+    #[test]
+    fn memory_stores_a_word() {
+        let mut mem = Memory::new(64 * 1024).unwrap();
+
+        mem.store_word(0xfffc, 0x1234).unwrap();
+        assert_eq!(0x34, mem.load(0xfffc).unwrap());
+        assert_eq!(0x12, mem.load(0xfffd).unwrap());
+
+        mem.store_word(0xffff, 0xabcd).unwrap();
+        assert_eq!(0xcd, mem.load(0xffff).unwrap());
+        assert_eq!(0xab, mem.load(0x0000).unwrap());
+    }
+
     #[test]
     fn memory_can_load_whole_memory() {
         let rom = vec![0xff; 65536];
         let mut mem = Memory::new(64*1024).unwrap();
-        mem.load_rom(0x0000, &rom);
+        mem.load_rom(0x0000, &rom).unwrap();
         assert_eq!(rom, mem.data);
     }
 
     #[test]
-    #[should_panic]
-    fn memory_panics_on_load_rom_out_of_bounds(){
-        let rom = vec![0x00; 16];
+    fn atari_ram_is_mirrored() {
+        let mut mem = Memory::new_atari();
+
+        mem.store(0x0080, 0x42).unwrap();
+        assert_eq!(0x42, mem.load(0x0180).unwrap()); // the stack page sees the same RAM
+        assert_eq!(0x42, mem.load(0x0480).unwrap());
+
+        mem.store(0x01ff, 0x17).unwrap();
+        assert_eq!(0x17, mem.load(0x00ff).unwrap());
+    }
+
+    #[test]
+    fn atari_tia_registers_are_mirrored() {
+        let mut mem = Memory::new_atari();
+
+        mem.store(0x0009, 0x2c).unwrap(); // COLUBK
+        assert_eq!(0x2c, mem.load(0x0049).unwrap());
+        assert_eq!(0x2c, mem.load(0x0109).unwrap());
+    }
+
+    /// remembers the writes it gets and answers reads with the register number
+    #[derive(Clone)]
+    struct WriteLog(std::rc::Rc<RefCell<Vec<(u8, u8)>>>);
+
+    impl MemoryMappedDevice for WriteLog {
+        fn read(&mut self, reg : u8) -> u8 {
+            reg
+        }
+
+        fn write(&mut self, reg : u8, value : u8) {
+            self.0.borrow_mut().push((reg, value));
+        }
+    }
+
+    #[test]
+    fn atari_tia_registers_go_to_the_device() {
+        let writes = std::rc::Rc::new(RefCell::new(Vec::new()));
+        let mut mem = Memory::new_atari();
+        mem.set_tia(Box::new(WriteLog(writes.clone())));
+
+        mem.store(0x0049, 0x2c).unwrap();
+        mem.store(0x0080, 0x11).unwrap(); // RAM, not the TIA
+        assert_eq!(*writes.borrow(), vec![(0x09, 0x2c)]);
+        assert_eq!(0x3f, mem.load(0x013f).unwrap());
+        assert_eq!(0x11, mem.load(0x0080).unwrap());
+    }
+
+    #[test]
+    fn flat_memory_is_not_mirrored() {
+        let mut mem = Memory::new(64*1024).unwrap();
+
+        mem.store(0x0080, 0x42).unwrap();
+        assert_eq!(0x00, mem.load(0x0180).unwrap());
+    }
+
+    #[test]
+    fn stores_to_rom_are_ignored() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0xf000, &[0xa9, 0x42]).unwrap();
+        mem.set_rom_region(0xf000, 0xffff);
+
+        assert!(mem.store(0xf000, 0x00).is_ok());
+        assert_eq!(0xa9, mem.load(0xf000).unwrap());
+
+        mem.store(0xefff, 0x11).unwrap(); // right below the region is still RAM
+        assert_eq!(0x11, mem.load(0xefff).unwrap());
+    }
+
+    #[test]
+    fn stores_to_rom_can_error() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0xf000, &[0xa9, 0x42]).unwrap();
+        mem.set_rom_region(0xf000, 0xffff);
+        mem.set_rom_write_policy(RomWritePolicy::Error);
+
+        let err = mem.store(0xf001, 0x00).unwrap_err();
+        assert!(err.is_read_only());
+        assert_eq!(0x42, mem.load(0xf001).unwrap());
+        assert!(mem.store(0xefff, 0x00).is_ok());
+    }
+
+    #[test]
+    fn memory_errors_on_load_rom_out_of_bounds(){
+        let rom = vec![0xff; 16];
         let mut mem = Memory::new(16*1024).unwrap();
-        mem.load_rom(0xff00, &rom);
+        assert!(mem.load_rom(0xff00, &rom).is_err());
+        assert!(mem.load_rom(0x3ff8, &rom).is_err());
+        assert!(mem.data.iter().all(|byte| *byte == 0x00));
     }
 }
 
@@ -0,0 +1,232 @@
+use std::cell::Cell;
+use std::fs;
+use std::io;
+
+use super::*;
+use crate::bus::CartBackend;
+
+/// first address of the 4K cartridge window
+pub const CARTRIDGE_START : u16 = 0x1000;
+pub const CARTRIDGE_END : u16 = 0x1fff;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    /// only raw 2K and 4K images, and 8K F8 images are supported
+    UnsupportedSize(usize)
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartridgeError::Io(e) => write!(f, "Could not read cartridge: {}", e),
+            CartridgeError::UnsupportedSize(size) => write!(f, "Unsupported cartridge size: {} bytes", size)
+        }
+    }
+}
+
+impl Error for CartridgeError {}
+
+impl From<io::Error> for CartridgeError {
+    fn from(e: io::Error) -> Self {
+        CartridgeError::Io(e)
+    }
+}
+
+/// size of the cartridge window, and of each bank
+const BANK_SIZE : usize = 0x1000;
+
+/// F8 hotspots, relative to the start of the window. touching them selects bank 0 or 1
+const F8_HOTSPOTS : [u16; 2] = [0x0ff8, 0x0ff9];
+
+/// the ROM visible in the cartridge window. images smaller than the window repeat
+/// because the cartridge doesn't decode the higher address lines.
+/// bigger images are split in 4K banks, switched by touching the hotspots
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Cartridge {
+    #[cfg_attr(feature = "serde", serde(with = "super::hex_bytes"))]
+    rom : Vec<u8>,
+    /// reads switch banks too, and those only borrow the memory
+    bank : Cell<usize>
+}
+
+impl Cartridge {
+    /// takes raw 2K and 4K images, and 8K F8 images
+    pub fn new(image : &[u8]) -> Result<Cartridge, CartridgeError> {
+        match image.len() {
+            0x0800 | 0x1000 | 0x2000 => (),
+            size => return Err(CartridgeError::UnsupportedSize(size))
+        }
+
+        // F8 carts come up with the last bank, which has the reset vector
+        let bank = (image.len() / BANK_SIZE).saturating_sub(1);
+        Ok(Cartridge { rom: image.to_vec(), bank: Cell::new(bank) })
+    }
+
+    /// switches banks when `offset` is a hotspot. reads and writes both count
+    pub(super) fn touch(&self, offset : u16) {
+        if self.rom.len() <= BANK_SIZE {
+            return;
+        }
+        if let Some(bank) = F8_HOTSPOTS.iter().position(|&hotspot| hotspot == offset) {
+            self.bank.set(bank);
+        }
+    }
+
+    fn banks(&self) -> usize {
+        self.rom.len().div_ceil(BANK_SIZE)
+    }
+
+    /// `offset` is relative to the start of the window
+    pub(super) fn read(&self, offset : u16) -> u8 {
+        self.touch(offset);
+        self.peek(offset)
+    }
+
+    /// the byte at `offset` in the current bank, without switching
+    pub(super) fn peek(&self, offset : u16) -> u8 {
+        if self.rom.len() <= BANK_SIZE {
+            self.rom[offset as usize & (self.rom.len() - 1)]
+        } else {
+            self.rom[self.bank.get() * BANK_SIZE + offset as usize]
+        }
+    }
+}
+
+impl CartBackend for Cartridge {
+    fn read(&self, offset : u16) -> u8 {
+        Cartridge::read(self, offset)
+    }
+
+    fn peek(&self, offset : u16) -> u8 {
+        Cartridge::peek(self, offset)
+    }
+
+    fn write(&mut self, offset : u16, _value : u8) {
+        self.touch(offset);
+    }
+}
+
+impl Memory {
+    /// a 2600 memory with the raw cartridge image at `path` in the cartridge window
+    pub fn load_cartridge_file(path : &str) -> Result<Memory, CartridgeError> {
+        let image = fs::read(path)?;
+        Memory::with_cartridge(&image)
+    }
+
+    /// a 2600 memory with `image` in the cartridge window, which is read-only.
+    /// 2K images show up in both halves of the window, 8K images bank switch like F8
+    pub fn with_cartridge(image : &[u8]) -> Result<Memory, CartridgeError> {
+        let cartridge = Cartridge::new(image)?;
+
+        let mut mem = Memory::new_atari();
+        mem.cartridge = Some(cartridge);
+        mem.set_rom_region(CARTRIDGE_START, CARTRIDGE_END);
+
+        Ok(mem)
+    }
+
+    /// the bank showing in the cartridge window, for save states
+    pub(crate) fn cartridge_bank(&self) -> Option<u8> {
+        self.cartridge.as_ref().map(|cartridge| cartridge.bank.get() as u8)
+    }
+
+    /// how many banks the cartridge has, None without a cartridge
+    pub(crate) fn cartridge_banks(&self) -> Option<u8> {
+        self.cartridge.as_ref().map(|cartridge| cartridge.banks() as u8)
+    }
+
+    /// switches to `bank`, which must be below `cartridge_banks`
+    pub(crate) fn set_cartridge_bank(&self, bank : u8) {
+        if let Some(cartridge) = &self.cartridge {
+            cartridge.bank.set(bank as usize);
+        }
+    }
+
+    /// the cartridge byte an (already canonical) address refers to, if any.
+    /// reading doesn't switch banks, `touch_cartridge` does
+    pub(super) fn cartridge_byte(&self, addr : u16) -> Option<u8> {
+        match &self.cartridge {
+            Some(cartridge) if (CARTRIDGE_START..=CARTRIDGE_END).contains(&addr) => {
+                Some(cartridge.peek(addr - CARTRIDGE_START))
+            },
+            _ => None
+        }
+    }
+
+    /// lets loads and writes to the hotspots switch banks, even though the window is read-only
+    pub(super) fn touch_cartridge(&self, addr : u16) {
+        if let Some(cartridge) = &self.cartridge {
+            if (CARTRIDGE_START..=CARTRIDGE_END).contains(&addr) {
+                cartridge.touch(addr - CARTRIDGE_START);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_a_4k_image_from_a_file() {
+        let image : Vec<u8> = (0..0x1000).map(|i| (i % 251) as u8).collect();
+        let path = std::env::temp_dir().join(format!("ataritron-{}.bin", std::process::id()));
+        fs::write(&path, &image).unwrap();
+
+        let mem = Memory::load_cartridge_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        let mem = mem.unwrap();
+
+        assert_eq!(0x00, mem.load(0x1000).unwrap());
+        assert_eq!(0x05, mem.load(0x1100).unwrap()); // 256 % 251
+        assert_eq!((0xffc % 251) as u8, mem.load(0x1ffc).unwrap());
+        assert_eq!(0x00, mem.load(0x0080).unwrap());
+    }
+
+    #[test]
+    fn mirrors_a_2k_image_into_the_upper_half() {
+        let image : Vec<u8> = (0..0x0800).map(|i| (i >> 3) as u8).collect();
+        let mem = Memory::with_cartridge(&image).unwrap();
+
+        assert_eq!(0x12, mem.load(0x1090).unwrap());
+        assert_eq!(0x12, mem.load(0x1890).unwrap());
+        assert_eq!(mem.load(0x17fc).unwrap(), mem.load(0x1ffc).unwrap());
+    }
+
+    #[test]
+    fn the_reset_vector_comes_from_the_cartridge_on_a_6507() {
+        let mut image = vec![0xea; 0x1000];
+        image[0xffc..0xffe].copy_from_slice(&[0x00, 0xf0]);
+        let mut mem = Memory::with_cartridge(&image).unwrap();
+        mem.set_6507_addressing(true);
+
+        assert_eq!(0x00, mem.load(0xfffc).unwrap());
+        assert_eq!(0xf0, mem.load(0xfffd).unwrap());
+        assert_eq!(0xea, mem.load(0xf000).unwrap());
+    }
+
+    #[test]
+    fn f8_hotspots_switch_banks() {
+        let mut image = vec![0xaa; 0x1000];
+        image.extend(vec![0xbb; 0x1000]);
+        let mut mem = Memory::with_cartridge(&image).unwrap();
+        assert_eq!(0xbb, mem.load(0x1000).unwrap());
+
+        mem.load(0x1ff8).unwrap();
+        assert_eq!(0xaa, mem.load(0x1000).unwrap());
+
+        mem.load(0x1ff9).unwrap();
+        assert_eq!(0xbb, mem.load(0x1000).unwrap());
+
+        mem.store(0x1ff8, 0x00).unwrap();
+        assert_eq!(0xaa, mem.load(0x1234).unwrap());
+    }
+
+    #[test]
+    fn rejects_odd_sizes() {
+        assert!(matches!(Memory::with_cartridge(&[0xea; 3000]), Err(CartridgeError::UnsupportedSize(3000))));
+        assert!(matches!(Memory::load_cartridge_file("/does/not/exist.bin"), Err(CartridgeError::Io(_))));
+    }
+}
@@ -0,0 +1,137 @@
+use super::*;
+
+/// receives the address of a RAM byte read before anything was written to it
+pub type UninitializedReadHook = Box<dyn FnMut(u16)>;
+
+/// RAM filled with a pattern instead of zeros, to shake out programs that expect a clean RAM
+#[derive(Clone)]
+pub(super) struct Poison {
+    value : u8,
+    /// the bytes `clear_ram` filled that nothing wrote to since
+    untouched : Vec<bool>
+}
+
+impl Memory {
+    /// makes `clear_ram`, and so a power on, fill RAM with `value` instead of zeros.
+    /// `None` goes back to zeros
+    pub fn set_ram_poison(&mut self, value : Option<u8>) {
+        self.poison = value.map(|value| Poison {
+            value,
+            untouched: vec![false; self.data.len()]
+        });
+    }
+
+    /// installs a callback for reads of poisoned RAM that nothing wrote to since it was cleared.
+    /// it can go in before or after `set_ram_poison`, but is only called while poisoning is on
+    pub fn set_uninitialized_read_hook(&mut self, f : UninitializedReadHook) {
+        self.uninitialized_read_hook = Some(RefCell::new(f));
+    }
+
+    /// the poison pattern and which bytes are still untouched, one bit each, for save states
+    pub(crate) fn poison_state(&self) -> Option<(u8, Vec<u8>)> {
+        self.poison.as_ref().map(|poison| {
+            let mut bits = vec![0; poison.untouched.len().div_ceil(8)];
+            for (addr, _) in poison.untouched.iter().enumerate().filter(|(_, untouched)| **untouched) {
+                bits[addr / 8] |= 1 << (addr % 8);
+            }
+            (poison.value, bits)
+        })
+    }
+
+    /// the opposite of `poison_state`. `bits` has to come from a memory of the same size
+    pub(crate) fn restore_poison(&mut self, state : Option<(u8, &[u8])>) {
+        self.poison = state.map(|(value, bits)| Poison {
+            value,
+            untouched: (0..self.data.len()).map(|addr| bits[addr / 8] & (1 << (addr % 8)) != 0).collect()
+        });
+    }
+
+    /// what `clear_ram` fills RAM with
+    pub(super) fn ram_fill(&self) -> u8 {
+        self.poison.as_ref().map_or(0, |poison| poison.value)
+    }
+
+    pub(super) fn mark_poisoned(&mut self, addr : u16) {
+        if let Some(poison) = self.poison.as_mut() {
+            poison.untouched[addr as usize] = true;
+        }
+    }
+
+    pub(super) fn mark_written(&mut self, addr : u16) {
+        if let Some(poison) = self.poison.as_mut() {
+            poison.untouched[addr as usize] = false;
+        }
+    }
+
+    pub(super) fn check_initialized(&self, addr : u16) {
+        if let Some(poison) = &self.poison {
+            if poison.untouched[addr as usize] {
+                if let Some(hook) = &self.uninitialized_read_hook {
+                    (hook.borrow_mut())(addr);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::cpu::Cpu;
+
+    #[test]
+    fn poisoned_ram_reads_the_pattern_until_written() {
+        let mut image = vec![0xea; 0x1000];
+        image[0xffc..0xffe].copy_from_slice(&[0x00, 0xf0]);
+        let mut mem = Memory::with_cartridge(&image).unwrap();
+        let reads = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&reads);
+        mem.set_ram_poison(Some(0xa5));
+        mem.set_uninitialized_read_hook(Box::new(move |addr| sink.borrow_mut().push(addr)));
+        let mut cpu = Cpu::new(mem);
+        cpu.power_on().unwrap();
+
+        assert_eq!(0xa5, cpu.memory().load(0x0080).unwrap());
+        assert_eq!(0xa5, cpu.memory().load(0x01ff).unwrap()); // mirror of 0xff
+
+        cpu.memory_mut().store(0x0080, 0x00).unwrap();
+        assert_eq!(0x00, cpu.memory().load(0x0080).unwrap());
+        assert_eq!(vec![0x0080, 0x00ff], *reads.borrow());
+    }
+
+    #[test]
+    fn the_hook_can_go_in_before_the_poison() {
+        let mut mem = Memory::new_atari();
+        let reads = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&reads);
+        mem.set_uninitialized_read_hook(Box::new(move |addr| sink.borrow_mut().push(addr)));
+        mem.clear_ram();
+        mem.load(0x0080).unwrap(); // not poisoned yet, so no call
+
+        mem.set_ram_poison(Some(0xa5));
+        mem.clear_ram();
+        mem.load(0x0081).unwrap();
+        mem.set_ram_poison(None);
+        mem.set_ram_poison(Some(0x5a));
+        mem.clear_ram();
+        mem.load(0x0082).unwrap();
+
+        assert_eq!(vec![0x0081, 0x0082], *reads.borrow());
+    }
+
+    #[test]
+    fn ram_is_zeroed_without_poison() {
+        let mut mem = Memory::new(64*1024).unwrap();
+        mem.load_rom(0xfffc, &[0x00, 0xf0]).unwrap();
+        mem.set_rom_region(0xf000, 0xffff);
+        mem.store(0x0200, 0x12).unwrap();
+        mem.set_ram_poison(Some(0xff));
+        mem.set_ram_poison(None);
+        let mut cpu = Cpu::new(mem);
+        cpu.power_on().unwrap();
+
+        assert_eq!(0x00, cpu.memory().load(0x0200).unwrap());
+    }
+}
@@ -0,0 +1,276 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// register offsets inside the RIOT window (0x280-0x29f)
+pub const SWCHA : u8 = 0x00;
+pub const SWCHB : u8 = 0x02;
+pub const INTIM : u8 = 0x04;
+pub const TIMINT : u8 = 0x05;
+pub const TIM1T : u8 = 0x14;
+pub const TIM8T : u8 = 0x15;
+pub const TIM64T : u8 = 0x16;
+pub const T1024T : u8 = 0x17;
+
+/// directions held on one joystick
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Joystick {
+    pub up : bool,
+    pub down : bool,
+    pub left : bool,
+    pub right : bool
+}
+
+/// the switches on the console itself. `true` means pressed, or set to A for the difficulties
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsoleSwitches {
+    pub reset : bool,
+    pub select : bool,
+    pub color : bool,
+    pub left_difficulty_a : bool,
+    pub right_difficulty_a : bool
+}
+
+impl Default for ConsoleSwitches {
+    fn default() -> Self {
+        ConsoleSwitches {
+            reset: false,
+            select: false,
+            color: true,
+            left_difficulty_a: false,
+            right_difficulty_a: false
+        }
+    }
+}
+
+/// the 6532 RIOT: 128 bytes of RAM (kept by `Memory`), I/O ports and a programmable timer.
+///
+/// the timer counts down once every `interval` cycles. once it goes past zero it wraps
+/// to 0xff and keeps decrementing once per cycle until it's written again.
+/// the cpu ticks it after each instruction, so writes land a few cycles early
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Riot {
+    timer : u8,
+    interval : u16,
+    /// cycles left until the next decrement
+    prescaler : u16,
+    underflowed : bool,
+    /// joystick directions, active low. player 0 on the high nibble
+    swcha : u8,
+    /// console switches, active low except color and difficulties
+    swchb : u8
+}
+
+impl Riot {
+    pub fn new() -> Riot {
+        Riot {
+            timer: 0,
+            interval: 1024,
+            prescaler: 1024,
+            underflowed: false,
+            swcha: 0xff,
+            swchb: Riot::encode_switches(ConsoleSwitches::default())
+        }
+    }
+
+    /// the timer starts over like after a power cycle. the joysticks and console switches
+    /// are physical, so they stay as they are
+    pub fn reset(&mut self) {
+        *self = Riot { swcha: self.swcha, swchb: self.swchb, ..Riot::new() };
+    }
+
+    /// port 0 is the left joystick, port 1 the right one
+    pub fn set_joystick(&mut self, port : u8, joystick : Joystick) {
+        // right, left, down, up from the high bit down
+        let pressed = (joystick.right as u8) << 3
+            | (joystick.left as u8) << 2
+            | (joystick.down as u8) << 1
+            | joystick.up as u8;
+        let shift = if port == 0 { 4 } else { 0 };
+
+        self.swcha = (self.swcha & !(0x0f << shift)) | (!pressed & 0x0f) << shift;
+    }
+
+    pub fn set_console_switches(&mut self, switches : ConsoleSwitches) {
+        self.swchb = Riot::encode_switches(switches);
+    }
+
+    fn encode_switches(switches : ConsoleSwitches) -> u8 {
+        // unused bits read as set
+        0b0011_0100
+            | (switches.right_difficulty_a as u8) << 7
+            | (switches.left_difficulty_a as u8) << 6
+            | (switches.color as u8) << 3
+            | (!switches.select as u8) << 1
+            | !switches.reset as u8
+    }
+
+    pub fn read(&self, reg : u8) -> u8 {
+        if reg & 0x04 == 0 {
+            match reg & 0x03 {
+                SWCHA => self.swcha,
+                SWCHB => self.swchb,
+                _ => 0x00 // data direction registers, everything is an input
+            }
+        } else if reg & 0x01 == 0 {
+            self.timer
+        } else if self.underflowed {
+            0x80
+        } else {
+            0x00
+        }
+    }
+
+    pub fn write(&mut self, reg : u8, value : u8) {
+        if reg & 0x14 != 0x14 {
+            return;
+        }
+
+        // A3 only enables the timer interrupt, which the 2600 doesn't wire up
+        let interval = match reg & 0x03 {
+            0 => 1,
+            1 => 8,
+            2 => 64,
+            _ => 1024
+        };
+
+        self.timer = value;
+        self.interval = interval;
+        self.prescaler = interval;
+        self.underflowed = false;
+    }
+
+    pub fn tick(&mut self, cycles : u8) {
+        for _ in 0..cycles {
+            if self.underflowed {
+                self.timer = self.timer.wrapping_sub(1);
+                continue;
+            }
+
+            self.prescaler -= 1;
+            if self.prescaler == 0 {
+                self.prescaler = self.interval;
+                if self.timer == 0 {
+                    self.underflowed = true;
+                }
+                self.timer = self.timer.wrapping_sub(1);
+            }
+        }
+    }
+}
+
+impl Riot {
+    /// the timer as save states store it: the counter, the interval, what's left of the
+    /// prescaler and whether it underflowed. the ports follow the inputs, so they aren't saved
+    pub(crate) fn timer_state(&self) -> [u8; 6] {
+        let [interval_low, interval_high] = self.interval.to_le_bytes();
+        let [prescaler_low, prescaler_high] = self.prescaler.to_le_bytes();
+        [self.timer, interval_low, interval_high, prescaler_low, prescaler_high, self.underflowed as u8]
+    }
+
+    /// a copy running the timer from `state`, None if no timer write could have produced it
+    pub(crate) fn with_timer_state(&self, state : &[u8; 6]) -> Option<Riot> {
+        let interval = u16::from_le_bytes([state[1], state[2]]);
+        let prescaler = u16::from_le_bytes([state[3], state[4]]);
+        if ![1, 8, 64, 1024].contains(&interval) || !(1..=interval).contains(&prescaler) || state[5] > 1 {
+            return None;
+        }
+
+        Some(Riot {
+            timer: state[0],
+            interval,
+            prescaler,
+            underflowed: state[5] == 1,
+            ..self.clone()
+        })
+    }
+}
+
+impl Default for Riot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joysticks_are_active_low() {
+        let mut riot = Riot::new();
+        assert_eq!(0xff, riot.read(SWCHA));
+
+        riot.set_joystick(0, Joystick { up: true, ..Default::default() });
+        assert_eq!(0b1110_1111, riot.read(SWCHA));
+
+        riot.set_joystick(1, Joystick { right: true, down: true, ..Default::default() });
+        assert_eq!(0b1110_0101, riot.read(SWCHA));
+
+        riot.set_joystick(0, Joystick::default());
+        assert_eq!(0b1111_0101, riot.read(SWCHA));
+    }
+
+    #[test]
+    fn console_switches() {
+        let mut riot = Riot::new();
+        assert_eq!(0b0011_1111, riot.read(SWCHB));
+
+        riot.set_console_switches(ConsoleSwitches {
+            reset: true,
+            color: false,
+            left_difficulty_a: true,
+            ..Default::default()
+        });
+        assert_eq!(0b0111_0110, riot.read(SWCHB));
+    }
+
+    #[test]
+    fn t1024t_decrements_every_1024_cycles() {
+        let mut riot = Riot::new();
+        riot.write(T1024T, 0x02);
+
+        for _ in 0..4 {
+            riot.tick(255);
+        }
+        riot.tick(3);
+        assert_eq!(0x02, riot.read(INTIM));
+        riot.tick(1);
+        assert_eq!(0x01, riot.read(INTIM));
+    }
+
+    #[test]
+    fn tim8t_and_tim64t_use_their_intervals() {
+        let mut riot = Riot::new();
+
+        riot.write(TIM8T, 0x10);
+        riot.tick(16);
+        assert_eq!(0x0e, riot.read(INTIM));
+
+        riot.write(TIM64T, 0x10);
+        riot.tick(128);
+        assert_eq!(0x0e, riot.read(INTIM));
+    }
+
+    #[test]
+    fn underflow_decrements_every_cycle() {
+        let mut riot = Riot::new();
+        riot.write(TIM64T, 0x01);
+
+        riot.tick(64);
+        assert_eq!(0x00, riot.read(INTIM));
+        assert_eq!(0x00, riot.read(TIMINT));
+
+        riot.tick(64);
+        assert_eq!(0xff, riot.read(INTIM));
+        assert_eq!(0x80, riot.read(TIMINT));
+
+        riot.tick(3);
+        assert_eq!(0xfc, riot.read(INTIM));
+
+        riot.write(TIM1T, 0x05); // writing restarts the normal count
+        assert_eq!(0x00, riot.read(TIMINT));
+        riot.tick(2);
+        assert_eq!(0x03, riot.read(INTIM));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::bus::MemoryMappedDevice;
+
+/// write registers, relative to the TIA window (0x00-0x3f)
+pub const VSYNC : u8 = 0x00;
+pub const VBLANK : u8 = 0x01;
+pub const WSYNC : u8 = 0x02;
+pub const COLUP0 : u8 = 0x06;
+pub const COLUP1 : u8 = 0x07;
+pub const COLUPF : u8 = 0x08;
+pub const COLUBK : u8 = 0x09;
+pub const CXCLR : u8 = 0x2c;
+
+/// collision registers, read from the low 3 bits of the address. each has two latches,
+/// on D7 and D6
+pub const CXM0P : u8 = 0x00;
+pub const CXM1P : u8 = 0x01;
+pub const CXP0FB : u8 = 0x02;
+pub const CXP1FB : u8 = 0x03;
+pub const CXM0FB : u8 = 0x04;
+pub const CXM1FB : u8 = 0x05;
+pub const CXBLPF : u8 = 0x06;
+pub const CXPPMM : u8 = 0x07;
+
+/// cpu cycles in a scanline (228 color clocks / 3)
+pub const CYCLES_PER_SCANLINE : u8 = 76;
+
+/// registers, collisions, beam and wsync
+const STATE_SIZE : usize = 0x40 + 8 + 1 + 1;
+
+/// the 2600's video and audio chip. only a stub for now: reads with A3 clear (0x00-0x07,
+/// 0x10-0x17, ...) give the collision registers, so VSYNC, VBLANK or COLUP0 can't be read back.
+/// the others, like COLUPF and COLUBK, give back what was last written, so games can poke at
+/// them and tests can look. it follows the beam closely enough for WSYNC to work
+#[derive(Debug, Clone)]
+pub struct Tia {
+    registers : [u8; 0x40],
+    /// collision latches, D7 and D6 of each register
+    collisions : [u8; 8],
+    /// cpu cycles since the current scanline started
+    beam : u8,
+    /// WSYNC was written, so RDY stays low until the scanline ends
+    wsync : bool
+}
+
+impl Tia {
+    pub fn new() -> Tia {
+        Tia { registers: [0x00; 0x40], collisions: [0x00; 8], beam: 0, wsync: false }
+    }
+
+    /// sets collision latches, until there are objects to collide. `bits` goes to D7 and D6
+    /// of the collision register `reg`, like `CXM0P`
+    pub fn inject_collision(&mut self, reg : u8, bits : u8) {
+        self.collisions[(reg & 0x07) as usize] |= bits & 0xc0;
+    }
+}
+
+impl Default for Tia {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryMappedDevice for Tia {
+    fn read(&mut self, reg : u8) -> u8 {
+        // the read registers only decode A0-A3
+        if reg & 0x08 == 0 {
+            return self.collisions[(reg & 0x07) as usize];
+        }
+        self.registers[(reg & 0x3f) as usize]
+    }
+
+    fn write(&mut self, reg : u8, value : u8) {
+        match reg & 0x3f {
+            WSYNC => self.wsync = true,
+            CXCLR => self.collisions = [0x00; 8],
+            _ => ()
+        }
+        self.registers[(reg & 0x3f) as usize] = value;
+    }
+
+    fn tick(&mut self, cycles : u8) {
+        for _ in 0..cycles {
+            self.beam += 1;
+            if self.beam == CYCLES_PER_SCANLINE {
+                self.beam = 0;
+                self.wsync = false;
+            }
+        }
+    }
+
+    fn ready(&self) -> bool {
+        !self.wsync
+    }
+
+    fn reset(&mut self) {
+        *self = Tia::new();
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        let mut state = Vec::with_capacity(STATE_SIZE);
+        state.extend_from_slice(&self.registers);
+        state.extend_from_slice(&self.collisions);
+        state.push(self.beam);
+        state.push(self.wsync as u8);
+        state
+    }
+
+    fn load_state(&mut self, state : &[u8]) -> bool {
+        if state.len() != STATE_SIZE || state[0x48] >= CYCLES_PER_SCANLINE || state[0x49] > 1 {
+            return false;
+        }
+
+        self.registers.copy_from_slice(&state[..0x40]);
+        self.collisions.copy_from_slice(&state[0x40..0x48]);
+        self.beam = state[0x48];
+        self.wsync = state[0x49] == 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registers_keep_what_was_written() {
+        let mut tia = Tia::new();
+        tia.write(COLUBK, 0x9a);
+        tia.write(COLUPF, 0x44);
+
+        assert_eq!(0x9a, tia.read(COLUBK));
+        assert_eq!(0x44, tia.read(COLUPF));
+    }
+
+    #[test]
+    fn registers_with_a3_clear_read_as_collisions() {
+        let mut tia = Tia::new();
+        tia.write(COLUP0, 0x55);
+        tia.write(VBLANK, 0x02);
+        tia.inject_collision(CXBLPF, 0x80);
+
+        assert_eq!(0x80, tia.read(COLUP0)); // same address as CXBLPF
+        assert_eq!(0x00, tia.read(VBLANK)); // CXM1P
+    }
+
+    #[test]
+    fn collisions_latch_until_cxclr() {
+        let mut tia = Tia::new();
+        assert_eq!(0x00, tia.read(CXM0P));
+
+        tia.inject_collision(CXM0P, 0x80);
+        tia.inject_collision(CXM0P, 0x40);
+        tia.inject_collision(CXPPMM, 0xff);
+        assert_eq!(0xc0, tia.read(CXM0P));
+        assert_eq!(0xc0, tia.read(0x30 | CXM0P)); // mirrored
+        assert_eq!(0xc0, tia.read(CXPPMM));
+        assert_eq!(0x00, tia.read(CXBLPF));
+
+        tia.write(CXCLR, 0x00);
+        for reg in CXM0P..=CXPPMM {
+            assert_eq!(0x00, tia.read(reg));
+        }
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let mut tia = Tia::new();
+        tia.write(COLUBK, 0x9a);
+        tia.inject_collision(CXPPMM, 0x40);
+        tia.tick(30);
+        tia.write(WSYNC, 0x00);
+        let state = tia.save_state();
+
+        let mut restored = Tia::new();
+        assert!(restored.load_state(&state));
+        assert_eq!(state, restored.save_state());
+        assert!(!restored.ready());
+        restored.tick(CYCLES_PER_SCANLINE - 30);
+        assert!(restored.ready());
+
+        let mut bad_beam = state.clone();
+        bad_beam[0x48] = CYCLES_PER_SCANLINE;
+        assert!(!restored.load_state(&bad_beam));
+        assert!(!restored.load_state(&state[1..]));
+    }
+
+    #[test]
+    fn wsync_holds_rdy_until_the_scanline_ends() {
+        let mut tia = Tia::new();
+        tia.tick(20);
+        tia.write(WSYNC, 0x00);
+        assert!(!tia.ready());
+
+        tia.tick(55);
+        assert!(!tia.ready());
+        tia.tick(1);
+        assert!(tia.ready());
+
+        tia.write(WSYNC, 0x00);
+        tia.reset();
+        assert!(tia.ready());
+    }
+}
@@ -0,0 +1,57 @@
+//! runs Klaus Dormann's 6502 functional test (https://github.com/Klaus2m5/6502_65C02_functional_tests).
+//!
+//! the binary isn't shipped with the repo. assemble `6502_functional_test.a65` (or grab the
+//! prebuilt `6502_functional_test.bin`) and point `KLAUS_FUNCTIONAL_TEST` at it:
+//!
+//! ```text
+//! KLAUS_FUNCTIONAL_TEST=path/to/6502_functional_test.bin cargo test --test klaus_dormann -- --nocapture
+//! ```
+//!
+//! the image covers the whole 64K and starts at $0400. the test ends by jumping to itself,
+//! at $3469 for the prebuilt binary; set `KLAUS_SUCCESS_PC` (hex) if yours was assembled differently.
+//! without the variable the test is skipped
+
+use std::env;
+use std::fs;
+
+use ataritron::cpu::Cpu;
+use ataritron::memory::Memory;
+
+const START_PC : u16 = 0x0400;
+const DEFAULT_SUCCESS_PC : u16 = 0x3469;
+/// the full run is around 30 million instructions
+const MAX_INSTRUCTIONS : u64 = 100_000_000;
+
+#[test]
+fn functional_test_reaches_the_success_trap() {
+    let path = match env::var("KLAUS_FUNCTIONAL_TEST") {
+        Ok(path) => path,
+        Err(_) => {
+            eprintln!("KLAUS_FUNCTIONAL_TEST is not set, skipping");
+            return;
+        }
+    };
+    let success_pc = env::var("KLAUS_SUCCESS_PC")
+        .map(|pc| u16::from_str_radix(pc.trim_start_matches("0x"), 16).expect("KLAUS_SUCCESS_PC is not hex"))
+        .unwrap_or(DEFAULT_SUCCESS_PC);
+
+    let image = fs::read(&path).expect("could not read the test binary");
+    let mut mem = Memory::new(0xffff + 1).unwrap();
+    mem.load_rom(0x0000, &image).unwrap();
+    let mut cpu = Cpu::builder(mem).pc(START_PC).build();
+
+    for _ in 0..MAX_INSTRUCTIONS {
+        let pc = cpu.pc();
+        if let Err(e) = cpu.step() {
+            panic!("stopped at ${:04X}: {}", pc, e);
+        }
+
+        // every test failure and the final success are a jump or branch to itself
+        if cpu.pc() == pc {
+            assert_eq!(success_pc, pc, "trapped at ${:04X}, state {:?}", pc, cpu.state());
+            return;
+        }
+    }
+
+    panic!("no trap after {} instructions, pc is ${:04X}", MAX_INSTRUCTIONS, cpu.pc());
+}
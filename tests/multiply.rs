@@ -0,0 +1,71 @@
+//! runs a shift-add 8x8 multiply, which leans on shifts, the carry chain, branches and
+//! indexed addressing all at once
+
+use ataritron::cpu::{Cpu, RunResult};
+use ataritron::memory::Memory;
+
+const ORG : u16 = 0x1000;
+const MULTIPLICAND : u16 = 0x80;
+const MULTIPLIER : u16 = 0x81;
+/// the product goes to $82,X with X = 2
+const PRODUCT : u16 = 0x84;
+
+/// shifts the product left and adds the multiplicand for each multiplier bit, high bit first.
+/// the low byte of the product lives in A, the high byte in $83,X
+const MULTIPLY : [u8; 28] = [
+    0xa9, 0x00,       // $1000 LDA #$00
+    0xa2, 0x02,       // $1002 LDX #$02
+    0x95, 0x83,       // $1004 STA $83,X
+    0xa0, 0x08,       // $1006 LDY #$08
+    0x0a,             // $1008 loop: ASL A
+    0x36, 0x83,       // $1009 ROL $83,X
+    0x06, 0x81,       // $100b ASL $81
+    0x90, 0x07,       // $100d BCC skip
+    0x18,             // $100f CLC
+    0x65, 0x80,       // $1010 ADC $80
+    0x90, 0x02,       // $1012 BCC skip
+    0xf6, 0x83,       // $1014 INC $83,X
+    0x88,             // $1016 skip: DEY
+    0xd0, 0xef,       // $1017 BNE loop
+    0x95, 0x82,       // $1019 STA $82,X
+    0x00              // $101b BRK
+];
+
+fn multiply(a : u8, b : u8) -> u16 {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(ORG, &MULTIPLY).unwrap();
+    mem.store(MULTIPLICAND, a).unwrap();
+    mem.store(MULTIPLIER, b).unwrap();
+    let mut cpu = Cpu::builder(mem).pc(ORG).build();
+
+    let result = cpu.run_until_break(10_000).unwrap();
+    assert!(matches!(result, RunResult::Break { .. }), "{:?}", result);
+
+    let mem = cpu.memory();
+    u16::from_le_bytes([mem.load(PRODUCT).unwrap(), mem.load(PRODUCT + 1).unwrap()])
+}
+
+#[test]
+fn multiplies_bytes() {
+    for (a, b, product) in [
+        (0, 0, 0),
+        (1, 1, 1),
+        (0, 200, 0),
+        (12, 10, 120),
+        (16, 16, 256),
+        (200, 3, 600),
+        (0x80, 0x02, 0x100),
+        (255, 255, 65025)
+    ] {
+        assert_eq!(product, multiply(a, b), "{} * {}", a, b);
+    }
+}
+
+#[test]
+fn multiplies_every_pair_of_nibbles() {
+    for a in 0..16u8 {
+        for b in 0..16u8 {
+            assert_eq!((a * 17) as u16 * b as u16, multiply(a * 17, b), "{} * {}", a * 17, b);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! cold boots a flat 64K machine through the public api
+
+use ataritron::cpu::{Cpu, CpuState};
+use ataritron::memory::Memory;
+
+fn machine() -> Memory {
+    let mut mem = Memory::new(64*1024).unwrap();
+    mem.load_rom(0xf000, &[
+        0xa2, 0x10, // LDX #$10
+        0xea        // NOP
+    ]).unwrap();
+    mem.load_rom(0xfffc, &[0x00, 0xf0]).unwrap();
+    mem.set_rom_region(0xf000, 0xffff);
+    mem
+}
+
+#[test]
+fn power_on_clears_ram_and_registers() {
+    let mut cpu = Cpu::new(machine());
+    cpu.memory_mut().store(0x0080, 0x42).unwrap();
+    cpu.jump_to(0xf000);
+    cpu.step().unwrap();
+    assert_eq!(0x10, cpu.x());
+
+    cpu.power_on().unwrap();
+    assert_eq!(0x00, cpu.memory().load(0x0080).unwrap());
+    assert_eq!(0xa2, cpu.memory().load(0xf000).unwrap());
+    assert_eq!(CpuState {
+        pc: 0xf000,
+        a: 0x00,
+        x: 0x00,
+        y: 0x00,
+        sp: 0xfd,
+        sr: 0x24
+    }, cpu.state());
+}
+
+#[test]
+fn power_on_wipes_programs_outside_the_rom_region() {
+    let mut mem = machine();
+    mem.load_rom(0x0200, &[0xea]).unwrap();
+    let mut cpu = Cpu::new(mem);
+
+    cpu.power_on().unwrap();
+    assert_eq!(0x00, cpu.memory().load(0x0200).unwrap());
+    assert_eq!(0xea, cpu.memory().load(0xf002).unwrap());
+}
+
+#[test]
+fn power_on_lets_go_of_wsync() {
+    let mut image = vec![0xea; 0x1000];
+    image[0x000..0x002].copy_from_slice(&[0x85, 0x02]); // STA WSYNC
+    image[0xffc..0xffe].copy_from_slice(&[0x00, 0xf0]);
+    let mut mem = Memory::with_cartridge(&image).unwrap();
+    mem.set_6507_addressing(true);
+    let mut cpu = Cpu::new_from_reset(mem).unwrap();
+
+    assert_eq!(3, cpu.step().unwrap());
+    assert_eq!(1, cpu.step().unwrap()); // waiting for the end of the scanline
+    cpu.set_rdy(false);
+    cpu.set_irq_line(true);
+
+    cpu.power_on().unwrap();
+    assert_eq!(0, cpu.cycles());
+    assert_eq!(3, cpu.step().unwrap());
+    assert_eq!(0xf002, cpu.pc());
+}